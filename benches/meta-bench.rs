@@ -7,6 +7,7 @@ extern crate bencher;
 use bencher::Bencher;
 
 use pad::MetaFile;
+use pad::ReadBackend;
 use pad::ReadLevel;
 use rayon::prelude::*;
 use std::path::PathBuf;
@@ -22,14 +23,14 @@ const FILE_FILTER: &str = r"bss$";
 
 fn b1_parse(bench: &mut Bencher) {
     bench.iter(|| {
-        let meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         bencher::black_box(meta);
     });
 }
 
 fn b2_filter_path(bench: &mut Bencher) {
     bench.iter(|| {
-        let mut meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         meta.filter_by_path(PATH_FILTER).expect("path filter error");
         bencher::black_box(meta);
     });
@@ -37,7 +38,7 @@ fn b2_filter_path(bench: &mut Bencher) {
 
 fn b3_filter_file(bench: &mut Bencher) {
     bench.iter(|| {
-        let mut meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         meta.filter_by_file(FILE_FILTER).expect("path filter error");
         bencher::black_box(meta);
     });
@@ -45,7 +46,7 @@ fn b3_filter_file(bench: &mut Bencher) {
 
 fn b4_filter_path_and_file(bench: &mut Bencher) {
     bench.iter(|| {
-        let mut meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         meta.filter_by_path(PATH_FILTER).expect("path filter error");
         meta.filter_by_file(FILE_FILTER).expect("path filter error");
         bencher::black_box(meta);
@@ -54,7 +55,7 @@ fn b4_filter_path_and_file(bench: &mut Bencher) {
 
 fn b5_read_raw(bench: &mut Bencher) {
     bench.iter(|| {
-        let mut meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         meta.filter_by_path(PATH_FILTER).expect("path filter error");
         meta.filter_by_file(FILE_FILTER).expect("path filter error");
         meta.meta_table.par_iter().for_each(|mr| {
@@ -66,7 +67,7 @@ fn b5_read_raw(bench: &mut Bencher) {
 
 fn b6_read_decrypted(bench: &mut Bencher) {
     bench.iter(|| {
-        let mut meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         meta.filter_by_path(PATH_FILTER).expect("path filter error");
         meta.filter_by_file(FILE_FILTER).expect("path filter error");
         meta.meta_table.par_iter().for_each(|mr| {
@@ -78,7 +79,7 @@ fn b6_read_decrypted(bench: &mut Bencher) {
 
 fn b7_read_decompressed(bench: &mut Bencher) {
     bench.iter(|| {
-        let mut meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         meta.filter_by_path(PATH_FILTER).expect("path filter error");
         meta.filter_by_file(FILE_FILTER).expect("path filter error");
         meta.meta_table.par_iter().for_each(|mr| {
@@ -91,13 +92,55 @@ fn b7_read_decompressed(bench: &mut Bencher) {
 fn b8_extract(bench: &mut Bencher) {
     bench.iter(|| {
         let out = PathBuf::from("./").canonicalize().unwrap().join("bench-out");
-        let mut meta = MetaFile::new_from_path(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
         meta.filter_by_path(PATH_FILTER).expect("path filter error");
         meta.filter_by_file(FILE_FILTER).expect("path filter error");
         meta.extract_many(&ReadLevel::Decompress, &out).expect("extract failed");
     });
 }
 
+fn b9_read_into_decompressed(bench: &mut Bencher) {
+    bench.iter(|| {
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
+        meta.filter_by_path(PATH_FILTER).expect("path filter error");
+        meta.filter_by_file(FILE_FILTER).expect("path filter error");
+        meta.meta_table.par_iter().for_each_init(Vec::new, |buf, mr| {
+            meta.read_into(mr, &ReadLevel::Decompress, buf).expect("read failed");
+            bencher::black_box(&buf);
+        });
+    });
+}
+
+fn b10_filter_combined(bench: &mut Bencher) {
+    bench.iter(|| {
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
+        meta.filter(Some(PATH_FILTER), Some(FILE_FILTER)).expect("filter error");
+        bencher::black_box(meta);
+    });
+}
+
+fn b11_read_cached_decompressed(bench: &mut Bencher) {
+    bench.iter(|| {
+        let mut meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
+        meta.backend = ReadBackend::CachedFile;
+        meta.filter_by_path(PATH_FILTER).expect("path filter error");
+        meta.filter_by_file(FILE_FILTER).expect("path filter error");
+        meta.meta_table.par_iter().for_each(|mr| {
+            let buf = meta.read(mr, &ReadLevel::Decompress).expect("read failed");
+            bencher::black_box(buf);
+        });
+    });
+}
+
+fn b12_view_filter_path(bench: &mut Bencher) {
+    bench.iter(|| {
+        let meta = MetaFile::new(&ROOT, ICE_KEY).expect("meta parsing error");
+        let mut view = meta.view();
+        view.filter_by_path(PATH_FILTER).expect("path filter error");
+        bencher::black_box(view.len());
+    });
+}
+
 benchmark_group!(
     bench_meta,
     b1_parse,
@@ -108,5 +151,9 @@ benchmark_group!(
     b6_read_decrypted,
     b7_read_decompressed,
     b8_extract,
+    b9_read_into_decompressed,
+    b10_filter_combined,
+    b11_read_cached_decompressed,
+    b12_view_filter_path,
 );
 benchmark_main!(bench_meta);
@@ -0,0 +1,112 @@
+// Extraction into a single archive instead of loose files. Records are decoded in parallel and
+// handed through a bounded channel to the one thread that owns the (non `Sync`) archive writer.
+use crate::{checked_relative, ExtractStats, MetaFile, MetaRecord, PadError, ReadLevel};
+use rayon::prelude::*;
+use std::path::Path;
+
+impl MetaFile {
+    // Writes every record in the current selection to a new zip at `zip_path`, deflated, named
+    // by its logical path with forward slashes. Records that fail to decode are reported in
+    // the stats and left out; a failure writing the zip ends the run.
+    #[cfg(feature = "zip")]
+    pub fn extract_to_zip(
+        &self,
+        level: &ReadLevel,
+        zip_path: &Path,
+    ) -> Result<ExtractStats, PadError> {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(zip_path)?);
+        let options = zip::write::FileOptions::default();
+        let stats = self.decode_serially(level, |name, buf| {
+            zip.start_file(name, options)?;
+            std::io::Write::write_all(&mut zip, buf)?;
+            Ok(())
+        })?;
+        zip.finish()?;
+        Ok(stats)
+    }
+
+    // Streams every record in the current selection to `w` as a tar archive, e.g. for piping
+    // through gzip. Entries are regular files named by their logical path, mode 0644 and
+    // stamped with the time the run started. Failures are handled as in `extract_to_zip`.
+    #[cfg(feature = "tar")]
+    pub fn extract_to_tar<W: std::io::Write>(
+        &self,
+        level: &ReadLevel,
+        w: W,
+    ) -> Result<ExtractStats, PadError> {
+        let mtime = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut tar = tar::Builder::new(w);
+        let stats = self.decode_serially(level, |name, buf| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(buf.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(mtime);
+            tar.append_data(&mut header, name, buf)?;
+            Ok(())
+        })?;
+        tar.finish()?;
+        Ok(stats)
+    }
+
+    // Decodes the current selection on the rayon pool and calls `write` with each logical path
+    // and its bytes on the calling thread, in completion order.
+    fn decode_serially(
+        &self,
+        level: &ReadLevel,
+        mut write: impl FnMut(&str, &[u8]) -> Result<(), PadError>,
+    ) -> Result<ExtractStats, PadError> {
+        let started = std::time::Instant::now();
+        let (mut files, mut bytes, mut failures) = (0, 0, Vec::new());
+        std::thread::scope(|scope| {
+            let threads = self.install(rayon::current_num_threads);
+            let (tx, rx) = std::sync::mpsc::sync_channel(threads * 4);
+            scope.spawn(move || {
+                let _ = self.install(|| {
+                    self.meta_table.par_iter().try_for_each_with(tx, |tx, mr| {
+                        let (name, decoded) = match self.logical_path_string(mr) {
+                            Ok(name) => {
+                                let decoded = checked_relative(Path::new(&name))
+                                    .and_then(|_| self.read(mr, level));
+                                (name, decoded)
+                            }
+                            Err(e) => (String::new(), Err(e)),
+                        };
+                        tx.send((mr, name, decoded))
+                    })
+                });
+            });
+            for (mr, name, decoded) in rx {
+                match decoded {
+                    Ok(buf) => {
+                        write(&name, &buf)?;
+                        files += 1;
+                        bytes += buf.len() as u64;
+                    }
+                    Err(e) => {
+                        report_failure(&name, mr, &e);
+                        failures.push((mr.hash, e));
+                    }
+                }
+            }
+            Ok::<(), PadError>(())
+        })?;
+        Ok(ExtractStats {
+            files,
+            skipped: 0,
+            bytes,
+            elapsed: started.elapsed(),
+            failures,
+            cancelled: false,
+            not_found: Vec::new(),
+            renamed: Vec::new(),
+            collisions: Vec::new(),
+        })
+    }
+}
+
+fn report_failure(name: &str, mr: &MetaRecord, e: &PadError) {
+    log::warn!("failed {}: {} ({:?})", name, e, mr);
+}
@@ -0,0 +1,89 @@
+// Reads and extraction for tokio runtimes. Package blocks are read with tokio's file I/O and the
+// decrypt and decompress work runs on the blocking pool, so none of it stalls the runtime.
+//
+// Decrypting still fans out over rayon, on `thread_pool` if set and the global pool otherwise,
+// from inside the blocking task. Many concurrent calls therefore share one rayon pool rather than
+// each getting its own threads; bound the number in flight (e.g. with a semaphore) and size
+// `thread_pool` to the cores left over for decoding so tokio's blocking threads and rayon's
+// workers don't oversubscribe the machine.
+use crate::{
+    check_block_end, check_size, checked_relative, decode_block, open_package, ExtractOptions,
+    MetaFile, MetaRecord, PadError, ReadLevel,
+};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+impl MetaFile {
+    // Async `read`. Packages read through `source` are read on the blocking pool along with the
+    // decoding, since `PackageSource` is synchronous.
+    pub async fn read_async(
+        self: &Arc<Self>,
+        record: &MetaRecord,
+        level: ReadLevel,
+    ) -> Result<Vec<u8>, PadError> {
+        check_size(record, &level, self.max_decompressed_size)?;
+        let record = record.clone();
+        if self.source.is_some() {
+            let meta = Arc::clone(self);
+            return spawn_blocking(move || meta.install(|| meta.read(&record, &level))).await;
+        }
+        let mut buf = self.read_block_async(&record).await?;
+        if level == ReadLevel::Raw {
+            return Ok(buf);
+        }
+        let meta = Arc::clone(self);
+        spawn_blocking(move || {
+            let unencrypted = meta.is_unencrypted(&record)?;
+            meta.install(|| decode_block(&mut buf, &record, &level, &meta.ice, unencrypted))?;
+            Ok(buf)
+        })
+        .await
+    }
+
+    // Async `extract`, returning the number of bytes written. Written to the path `extract`
+    // would use.
+    pub async fn extract_async(
+        self: &Arc<Self>,
+        record: &MetaRecord,
+        level: ReadLevel,
+        out_path: &Path,
+    ) -> Result<u64, PadError> {
+        let rel_path = self.output_path(record, &ExtractOptions::default())?;
+        let out_file = out_path.join(checked_relative(&rel_path)?);
+        let buf = self.read_async(record, level).await?;
+        if let Some(parent) = out_file.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut f = tokio::fs::File::create(&out_file).await?;
+        f.write_all(&buf).await?;
+        f.flush().await?;
+        Ok(buf.len() as u64)
+    }
+
+    async fn read_block_async(&self, record: &MetaRecord) -> Result<Vec<u8>, PadError> {
+        // Opened on the blocking pool as tokio does, but through `open_package` for its share mode.
+        let (paz_path, share_packages) = (self.package_path(record), self.share_packages);
+        let f = spawn_blocking(move || Ok(open_package(&paz_path, share_packages)?)).await?;
+        let mut f = tokio::fs::File::from_std(f);
+        check_block_end(
+            record.package_id,
+            record.package_offset as u64,
+            record.sz_compressed as u64,
+            f.metadata().await?.len(),
+        )?;
+        f.seek(std::io::SeekFrom::Start(record.package_offset as u64))
+            .await?;
+        let mut buf = vec![0; record.sz_compressed as usize];
+        f.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+}
+
+async fn spawn_blocking<T: Send + 'static>(
+    op: impl FnOnce() -> Result<T, PadError> + Send + 'static,
+) -> Result<T, PadError> {
+    tokio::task::spawn_blocking(op)
+        .await
+        .map_err(|e| std::io::Error::other(e.to_string()))?
+}
@@ -0,0 +1,228 @@
+// The package block readers behind each `ReadBackend`, and the caller owned `PackageCache`.
+use crate::{check_block_end, open_package, MetaRecord, PadError};
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// How package blocks are fetched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadBackend {
+    /// Opens, seeks and reads the package for every record.
+    #[default]
+    File,
+    /// Keeps one shared descriptor per package and reads at an offset without seeking. Holds a
+    /// descriptor open for every package touched, so mind the open file limit when reading
+    /// across thousands of packages.
+    CachedFile,
+    /// Maps each package once and copies records straight out of the mapping.
+    Mmap,
+}
+
+/// Descriptors kept open by the `CachedFile` backend, keyed by package id.
+#[derive(Debug, Default)]
+pub struct OpenPackages {
+    files: Mutex<HashMap<u32, Arc<File>>>,
+}
+
+impl OpenPackages {
+    // Reads the start of `record`'s block into `buf`, which may be shorter than the block. The
+    // whole block is still checked against the package size, as the `File` backend does.
+    pub(crate) fn read_block(
+        &self,
+        paz_path: &Path,
+        record: &MetaRecord,
+        share_packages: bool,
+        buf: &mut [u8],
+    ) -> Result<(), PadError> {
+        let f = {
+            let mut files = self.files.lock().unwrap();
+            match files.get(&record.package_id) {
+                Some(f) => f.clone(),
+                None => {
+                    let f = Arc::new(open_existing_package(paz_path, share_packages)?);
+                    files.insert(record.package_id, f.clone());
+                    f
+                }
+            }
+        };
+        let offset = record.package_offset as u64;
+        check_block_end(
+            record.package_id,
+            offset,
+            record.sz_compressed as u64,
+            f.metadata()?.len(),
+        )?;
+        read_exact_at(&f, offset, buf)?;
+        Ok(())
+    }
+}
+
+// Positional reads leave no shared cursor behind so one descriptor can serve every thread.
+#[cfg(unix)]
+fn read_exact_at(f: &File, offset: u64, buf: &mut [u8]) -> std::io::Result<()> {
+    use std::os::unix::fs::FileExt;
+    f.read_exact_at(buf, offset)
+}
+
+#[cfg(windows)]
+fn read_exact_at(f: &File, mut offset: u64, mut buf: &mut [u8]) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    while !buf.is_empty() {
+        match f.seek_read(buf, offset)? {
+            0 => return Err(std::io::ErrorKind::UnexpectedEof.into()),
+            n => {
+                buf = &mut buf[n..];
+                offset += n as u64;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Packages mapped by the `Mmap` backend, keyed by package id. Maps stay alive for as long as
+/// the `MetaFile` does.
+#[derive(Debug, Default)]
+pub struct MappedPackages {
+    maps: Mutex<HashMap<u32, Arc<memmap2::Mmap>>>,
+}
+
+impl MappedPackages {
+    pub(crate) fn get(
+        &self,
+        package_id: u32,
+        paz_path: &Path,
+        share_packages: bool,
+    ) -> Result<Arc<memmap2::Mmap>, PadError> {
+        let mut maps = self.maps.lock().unwrap();
+        if let Some(map) = maps.get(&package_id) {
+            return Ok(map.clone());
+        }
+        let f = open_existing_package(paz_path, share_packages)?;
+        // Safety: packages are opened read-only; the mapping is only invalidated if another
+        // process truncates the package while it's mapped, which `ReadBackend::File` avoids.
+        let map = Arc::new(unsafe { memmap2::Mmap::map(&f)? });
+        maps.insert(package_id, map.clone());
+        Ok(map)
+    }
+}
+
+/// A package cache owned by the caller and handed to `MetaFile::read_with`, so its lifetime and
+/// size aren't tied to any one `MetaFile`. Several metas loaded from the same root, e.g. filtered
+/// copies, can share one. Entries are keyed by package path and the least recently used package
+/// is dropped once more than `max_open` are held; a handle still in use by a reader stays valid
+/// until that read finishes.
+#[derive(Debug)]
+pub struct PackageCache {
+    mapped: bool,
+    max_open: usize,
+    entries: Mutex<CacheEntries>,
+}
+
+#[derive(Debug, Default)]
+struct CacheEntries {
+    packages: HashMap<PathBuf, CachedPackage>,
+    // Package paths from least to most recently used.
+    order: std::collections::VecDeque<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+enum CachedPackage {
+    File(Arc<File>),
+    Map(Arc<memmap2::Mmap>),
+}
+
+impl PackageCache {
+    // Keeps up to `max_open` package descriptors open, read at an offset like `CachedFile`.
+    pub fn files(max_open: usize) -> Self {
+        Self::new(false, max_open)
+    }
+
+    // Keeps up to `max_open` packages mapped, read like `Mmap`.
+    pub fn mapped(max_open: usize) -> Self {
+        Self::new(true, max_open)
+    }
+
+    fn new(mapped: bool, max_open: usize) -> Self {
+        PackageCache {
+            mapped,
+            max_open: max_open.max(1),
+            entries: Mutex::default(),
+        }
+    }
+
+    // Number of packages currently held.
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().packages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Drops every held package.
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.packages.clear();
+        entries.order.clear();
+    }
+
+    pub(crate) fn read_block(
+        &self,
+        package_id: u32,
+        paz_path: &Path,
+        share_packages: bool,
+        offset: u64,
+        buf: &mut [u8],
+    ) -> Result<(), PadError> {
+        match self.get(paz_path, share_packages)? {
+            CachedPackage::File(f) => {
+                check_block_end(package_id, offset, buf.len() as u64, f.metadata()?.len())?;
+                read_exact_at(&f, offset, buf)?;
+            }
+            CachedPackage::Map(map) => {
+                let end = check_block_end(package_id, offset, buf.len() as u64, map.len() as u64)?;
+                buf.copy_from_slice(&map[offset as usize..end as usize]);
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self, paz_path: &Path, share_packages: bool) -> Result<CachedPackage, PadError> {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(package) = entries.packages.get(paz_path).cloned() {
+            if let Some(i) = entries.order.iter().position(|p| p == paz_path) {
+                let recent = entries.order.remove(i).unwrap();
+                entries.order.push_back(recent);
+            }
+            return Ok(package);
+        }
+        let f = open_existing_package(paz_path, share_packages)?;
+        let package = if self.mapped {
+            // Safety: as for `MappedPackages`.
+            CachedPackage::Map(Arc::new(unsafe { memmap2::Mmap::map(&f)? }))
+        } else {
+            CachedPackage::File(Arc::new(f))
+        };
+        while entries.packages.len() >= self.max_open {
+            match entries.order.pop_front() {
+                Some(oldest) => entries.packages.remove(&oldest),
+                None => break,
+            };
+        }
+        entries
+            .packages
+            .insert(paz_path.to_path_buf(), package.clone());
+        entries.order.push_back(paz_path.to_path_buf());
+        Ok(package)
+    }
+}
+
+// `open_package` with a missing package reported as `PadError::MissingPackage`, as every backend
+// reports it.
+fn open_existing_package(paz_path: &Path, share_packages: bool) -> Result<File, PadError> {
+    open_package(paz_path, share_packages).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => PadError::MissingPackage(paz_path.to_path_buf()),
+        _ => PadError::Io(e),
+    })
+}
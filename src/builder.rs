@@ -0,0 +1,80 @@
+// Collects the optional `MetaFile` settings so constructors don't need one variant per
+// combination. `MetaFile::new` stays the short path for the defaults.
+use crate::{read_meta, MetaFile, PadError, ReadBackend, DEFAULT_ENCODING, DEFAULT_META_FILE};
+use encoding_rs::Encoding;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct MetaFileBuilder {
+    key: Option<Vec<u8>>,
+    encoding: &'static Encoding,
+    meta_name: PathBuf,
+    backend: ReadBackend,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+}
+
+impl Default for MetaFileBuilder {
+    fn default() -> Self {
+        MetaFileBuilder {
+            key: None,
+            encoding: DEFAULT_ENCODING,
+            meta_name: PathBuf::from(DEFAULT_META_FILE),
+            backend: ReadBackend::default(),
+            thread_pool: None,
+        }
+    }
+}
+
+impl MetaFile {
+    pub fn builder() -> MetaFileBuilder {
+        MetaFileBuilder::default()
+    }
+}
+
+impl MetaFileBuilder {
+    // The ICE key the meta and packages are encrypted with. Required.
+    pub fn key(mut self, key: &[u8]) -> Self {
+        self.key = Some(key.to_vec());
+        self
+    }
+
+    // Encoding of the path and file names, EUC_KR by default.
+    pub fn encoding(mut self, encoding: &'static Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    // Meta file to parse, resolved as in `MetaFile::new_with_meta`. `pad00000.meta` by default.
+    pub fn meta_name(mut self, meta_name: impl Into<PathBuf>) -> Self {
+        self.meta_name = meta_name.into();
+        self
+    }
+
+    pub fn backend(mut self, backend: ReadBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    // Pool for parsing and for the built `MetaFile`'s extraction, so a host application can cap
+    // this crate's threads and leave the global rayon pool to its own work.
+    pub fn thread_pool(mut self, pool: Arc<rayon::ThreadPool>) -> Self {
+        self.thread_pool = Some(pool);
+        self
+    }
+
+    // Parses the meta under `root`, failing with `PadError::MissingKey` if no key was set.
+    pub fn build(&self, root: &Path) -> Result<MetaFile, PadError> {
+        let key = self.key.as_ref().ok_or(PadError::MissingKey)?;
+        let buf = read_meta(&root.join(&self.meta_name))?;
+        let parse =
+            || MetaFile::from_bytes_with_encoding(buf, root.to_path_buf(), key, self.encoding);
+        let mut meta = match &self.thread_pool {
+            Some(pool) => pool.install(parse)?,
+            None => parse()?,
+        };
+        meta.backend = self.backend;
+        meta.thread_pool = self.thread_pool.clone();
+        Ok(meta)
+    }
+}
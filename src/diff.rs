@@ -0,0 +1,81 @@
+// Comparison of two loaded metas by logical path, e.g. the same client before and after a patch.
+use crate::{ExtractOptions, ExtractStats, MetaFile, MetaRecord, PadError, ReadLevel};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+type PathRecords<'a> = Vec<(PathBuf, &'a MetaRecord)>;
+
+/// Logical paths that differ between two metas, each sorted, from `MetaFile::diff`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetaDiff {
+    /// Paths only in the newer meta.
+    pub added: Vec<PathBuf>,
+    /// Paths only in the older meta.
+    pub removed: Vec<PathBuf>,
+    /// Paths in both whose hash or original size differs.
+    pub changed: Vec<PathBuf>,
+}
+
+impl MetaFile {
+    // What changed from `other` (the older meta) to `self` over their current selections. A path
+    // several records resolve to is compared by its first record in meta table order.
+    pub fn diff(&self, other: &MetaFile) -> MetaDiff {
+        let paths = |records: PathRecords| {
+            let mut paths: Vec<_> = records.into_iter().map(|(path, _)| path).collect();
+            paths.sort_unstable();
+            paths
+        };
+        let (added, changed, removed) = self.diff_records(other);
+        MetaDiff {
+            added: paths(added),
+            removed: paths(removed),
+            changed: paths(changed),
+        }
+    }
+
+    // Extracts just the records whose logical path is new since `baseline` or whose hash or
+    // original size changed, as compared by `diff`, for a minimal patch dump.
+    pub fn extract_diff(
+        &self,
+        baseline: &MetaFile,
+        level: &ReadLevel,
+        out_path: &Path,
+    ) -> Result<ExtractStats, PadError> {
+        let (added, changed, _) = self.diff_records(baseline);
+        let records: Vec<_> = added.into_iter().chain(changed).map(|(_, mr)| mr).collect();
+        let cancel = AtomicBool::new(false);
+        let options = ExtractOptions::default();
+        self.extract_many_impl(&records, level, out_path, &options, &cancel, |_, _, _| ())
+    }
+
+    // Added and changed records of `self` and removed records of `other`, by logical path.
+    fn diff_records<'a>(
+        &'a self,
+        other: &'a MetaFile,
+    ) -> (PathRecords<'a>, PathRecords<'a>, PathRecords<'a>) {
+        let ours = self.records_by_path();
+        let mut theirs = other.records_by_path();
+        let (mut added, mut changed) = (Vec::new(), Vec::new());
+        for (path, mr) in ours {
+            match theirs.remove(&path) {
+                None => added.push((path, mr)),
+                Some(old) if is_changed(old, mr) => changed.push((path, mr)),
+                Some(_) => (),
+            }
+        }
+        (added, changed, theirs.into_iter().collect())
+    }
+
+    fn records_by_path(&self) -> HashMap<PathBuf, &MetaRecord> {
+        let mut by_path = HashMap::new();
+        for (mr, logical_path) in self.entries() {
+            by_path.entry(logical_path).or_insert(mr);
+        }
+        by_path
+    }
+}
+
+fn is_changed(old: &MetaRecord, new: &MetaRecord) -> bool {
+    old.hash != new.hash || old.sz_original != new.sz_original
+}
@@ -0,0 +1,25 @@
+// SHA-256 digests of decoded records for comparing extractions across runs, machines or game
+// patches without writing anything to disk.
+use crate::{MetaFile, MetaRecord, PadError, ReadLevel};
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+impl MetaFile {
+    // Digest of the bytes `read` returns for `record` at `level`.
+    pub fn digest(&self, record: &MetaRecord, level: &ReadLevel) -> Result<[u8; 32], PadError> {
+        let buf = self.read(record, level)?;
+        Ok(Sha256::digest(&buf).into())
+    }
+
+    // Logical path and digest of every record in the current selection, in meta table order,
+    // failing on the first record that can't be read.
+    pub fn digest_all(&self, level: &ReadLevel) -> Result<Vec<(PathBuf, [u8; 32])>, PadError> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .map(|mr| Ok((self.logical_path(mr)?, self.digest(mr, level)?)))
+                .collect()
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum PadError {
+    Io(std::io::Error),
+    MissingMeta(PathBuf),
+    MissingPackage(PathBuf),
+    MissingKey,
+    BadKeyLength(usize),
+    IndexOutOfRange {
+        table: &'static str,
+        index: usize,
+    },
+    Regex(regex::Error),
+    Glob(globset::Error),
+    Decompress(String),
+    SuspiciousSize {
+        sz_original: u32,
+        max_decompressed_size: u64,
+    },
+    UnsafePath(PathBuf),
+    SizeMismatch {
+        expected: u32,
+        got: usize,
+    },
+    OffsetOverflow {
+        package_id: u32,
+        end: u64,
+        package_size: u64,
+    },
+    #[cfg(feature = "serde")]
+    Json(serde_json::Error),
+    #[cfg(feature = "zip")]
+    Zip(zip::result::ZipError),
+}
+
+impl std::fmt::Display for PadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PadError::Io(e) => write!(f, "io error: {}", e),
+            PadError::MissingMeta(path) => write!(f, "meta file {} not found", path.display()),
+            PadError::MissingPackage(path) => write!(f, "package {} not found", path.display()),
+            PadError::MissingKey => write!(f, "no decryption key was given"),
+            PadError::BadKeyLength(len) => write!(f, "unsupported key length {}", len),
+            PadError::IndexOutOfRange { table, index } => {
+                write!(f, "index {} out of range for the {} table", index, table)
+            }
+            PadError::Regex(e) => write!(f, "invalid pattern: {}", e),
+            PadError::Glob(e) => write!(f, "invalid glob: {}", e),
+            PadError::Decompress(e) => write!(f, "decompression failed: {}", e),
+            PadError::SuspiciousSize {
+                sz_original,
+                max_decompressed_size,
+            } => write!(
+                f,
+                "suspicious original size {} exceeds the limit of {}",
+                sz_original, max_decompressed_size
+            ),
+            PadError::UnsafePath(path) => {
+                write!(f, "path {} escapes the output directory", path.display())
+            }
+            PadError::SizeMismatch { expected, got } => write!(
+                f,
+                "decoded {} bytes but the record expects {}",
+                got, expected
+            ),
+            PadError::OffsetOverflow {
+                package_id,
+                end,
+                package_size,
+            } => write!(
+                f,
+                "block ending at {} runs past the end of package {} ({} bytes)",
+                end, package_id, package_size
+            ),
+            #[cfg(feature = "serde")]
+            PadError::Json(e) => write!(f, "json error: {}", e),
+            #[cfg(feature = "zip")]
+            PadError::Zip(e) => write!(f, "zip error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PadError::Io(e) => Some(e),
+            PadError::Regex(e) => Some(e),
+            PadError::Glob(e) => Some(e),
+            #[cfg(feature = "serde")]
+            PadError::Json(e) => Some(e),
+            #[cfg(feature = "zip")]
+            PadError::Zip(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PadError {
+    fn from(e: std::io::Error) -> Self {
+        PadError::Io(e)
+    }
+}
+
+impl From<regex::Error> for PadError {
+    fn from(e: regex::Error) -> Self {
+        PadError::Regex(e)
+    }
+}
+
+impl From<globset::Error> for PadError {
+    fn from(e: globset::Error) -> Self {
+        PadError::Glob(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<serde_json::Error> for PadError {
+    fn from(e: serde_json::Error) -> Self {
+        PadError::Json(e)
+    }
+}
+
+#[cfg(feature = "zip")]
+impl From<zip::result::ZipError> for PadError {
+    fn from(e: zip::result::ZipError) -> Self {
+        PadError::Zip(e)
+    }
+}
@@ -0,0 +1,70 @@
+// Bob Jenkins' lookup3 `hashlittle`, the checksum assumed for the package table's `hash`.
+// The assumption hasn't been confirmed against a retail install, which is why callers get the
+// computed value alongside the stored one rather than just a verdict.
+
+use std::io::Read;
+
+// Bytes of the body read per call, a whole number of 12 byte blocks.
+const CHUNK_LEN: usize = 12 * 8192;
+
+// Hashes the next `len` bytes of `r`. The length seeds the state, so it has to be known up
+// front; the bytes are read in chunks rather than held at once.
+pub(crate) fn hashlittle(r: &mut dyn Read, len: u64, seed: u32) -> std::io::Result<u32> {
+    let init = 0xdeadbeef_u32.wrapping_add(len as u32).wrapping_add(seed);
+    let (mut a, mut b, mut c) = (init, init, init);
+    if len == 0 {
+        return Ok(c);
+    }
+
+    // The last 1 to 12 bytes go through `final_mix` rather than `mix`.
+    let tail_len = ((len - 1) % 12 + 1) as usize;
+    let mut body_len = len - tail_len as u64;
+    let mut chunk = vec![0u8; CHUNK_LEN.min(body_len as usize)];
+    while body_len > 0 {
+        let body = &mut chunk[..CHUNK_LEN.min(body_len as usize)];
+        r.read_exact(body)?;
+        for block in body.chunks_exact(12) {
+            a = a.wrapping_add(word(&block[0..4]));
+            b = b.wrapping_add(word(&block[4..8]));
+            c = c.wrapping_add(word(&block[8..12]));
+            mix(&mut a, &mut b, &mut c);
+        }
+        body_len -= body.len() as u64;
+    }
+    let mut last = [0u8; 12];
+    r.read_exact(&mut last[..tail_len])?;
+    a = a.wrapping_add(word(&last[0..4]));
+    b = b.wrapping_add(word(&last[4..8]));
+    c = c.wrapping_add(word(&last[8..12]));
+    final_mix(&mut a, &mut b, &mut c);
+    Ok(c)
+}
+
+fn word(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes(bytes.try_into().unwrap())
+}
+
+fn mix(a: &mut u32, b: &mut u32, c: &mut u32) {
+    *a = a.wrapping_sub(*c) ^ c.rotate_left(4);
+    *c = c.wrapping_add(*b);
+    *b = b.wrapping_sub(*a) ^ a.rotate_left(6);
+    *a = a.wrapping_add(*c);
+    *c = c.wrapping_sub(*b) ^ b.rotate_left(8);
+    *b = b.wrapping_add(*a);
+    *a = a.wrapping_sub(*c) ^ c.rotate_left(16);
+    *c = c.wrapping_add(*b);
+    *b = b.wrapping_sub(*a) ^ a.rotate_left(19);
+    *a = a.wrapping_add(*c);
+    *c = c.wrapping_sub(*b) ^ b.rotate_left(4);
+    *b = b.wrapping_add(*a);
+}
+
+fn final_mix(a: &mut u32, b: &mut u32, c: &mut u32) {
+    *c = (*c ^ *b).wrapping_sub(b.rotate_left(14));
+    *a = (*a ^ *c).wrapping_sub(c.rotate_left(11));
+    *b = (*b ^ *a).wrapping_sub(a.rotate_left(25));
+    *c = (*c ^ *b).wrapping_sub(b.rotate_left(16));
+    *a = (*a ^ *c).wrapping_sub(c.rotate_left(4));
+    *b = (*b ^ *a).wrapping_sub(a.rotate_left(14));
+    *c = (*c ^ *b).wrapping_sub(b.rotate_left(24));
+}
@@ -1,21 +1,357 @@
 use byteorder::LittleEndian;
 use byteorder::ReadBytesExt;
+use encoding_rs::Encoding;
 use ice::icefast::Ice;
 use rayon::prelude::*;
-use std::error::Error;
 use std::io::prelude::*;
 use std::io::Cursor;
 use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
+#[cfg(any(feature = "zip", feature = "tar"))]
+mod archive;
+#[cfg(feature = "tokio")]
+mod async_io;
+mod backend;
+mod builder;
+mod diff;
+#[cfg(feature = "sha2")]
+mod digest;
+pub mod error;
+mod hash;
+mod manifest;
+mod repack;
+mod sanitize;
+pub mod sniff;
+mod source;
+mod validate;
+mod view;
+
+pub use backend::{MappedPackages, OpenPackages, PackageCache, ReadBackend};
+pub use builder::MetaFileBuilder;
+pub use diff::MetaDiff;
+pub use error::PadError;
+pub use sanitize::{sanitize_windows_path, SanitizeNames};
+pub use source::{FsSource, PackageSource, ReadSeek};
+pub use validate::{ArchiveReport, PackageChecksum, PackageMismatch, ReportSection};
+pub use view::MetaView;
+
+/// How far a record's block is decoded. Each level includes the ones before it.
 #[derive(PartialOrd, Ord, PartialEq, Eq)]
 pub enum ReadLevel {
-    #[allow(dead_code)]
+    /// The block exactly as stored in the package, for repacking untouched blocks.
     Raw,
+    /// ICE decrypted, still quicklz compressed where the record is. `.dbss` blocks, and those of
+    /// any other `MetaFile::unencrypted_extensions`, are stored unencrypted so this leaves them as is.
     Decrypt,
+    /// Decrypted and decompressed to the record's original size.
     Decompress,
 }
 
+/// Codec applied to decoded records by `MetaFile::for_each_recompressed`.
+pub enum OutCodec {
+    /// Hands the decoded bytes through untouched.
+    Identity,
+    /// Recompresses with zstd at the given level.
+    #[cfg(feature = "zstd")]
+    Zstd(i32),
+}
+
+impl OutCodec {
+    fn encode(&self, buf: Vec<u8>) -> Result<Vec<u8>, PadError> {
+        match self {
+            OutCodec::Identity => Ok(buf),
+            #[cfg(feature = "zstd")]
+            OutCodec::Zstd(level) => Ok(zstd::bulk::compress(&buf, *level)?),
+        }
+    }
+}
+
+/// How a record's block is compressed, as detected from its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// The block is the file's bytes, up to cipher block padding.
+    None,
+    /// A quicklz compressed or stored block.
+    QuickLz,
+    /// A zstd frame.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+/// Options for `MetaFile::extract_many_with_options`.
+#[derive(Debug, Default)]
+pub struct ExtractOptions {
+    /// Writes a JSON index mapping each extracted file back to its meta record fields.
+    /// Relative paths are resolved against the output directory.
+    pub emit_repack_index: Option<PathBuf>,
+    /// Lowercases output paths for case-insensitive filesystems. As with any records sharing an
+    /// output path, only the first of those colliding once lowercased is extracted. The repack
+    /// index keeps the original casing.
+    pub lowercase_paths: bool,
+    /// Sniffs the decoded bytes and corrects the output extension when it disagrees with the
+    /// archive name. Each rename is logged.
+    pub fix_extensions: bool,
+    /// Signatures checked ahead of the built-in `sniff::SIGNATURES` table.
+    pub extra_signatures: Vec<sniff::Signature>,
+    /// The order records are read from their packages.
+    pub order: ExtractOrder,
+    /// What to do about output files that already exist.
+    pub overwrite: Overwrite,
+    /// Stops at the first failed record and returns its error instead of extracting the rest
+    /// and reporting failures in `ExtractStats::failures`. Records already in flight finish.
+    pub fail_fast: bool,
+    /// How output paths are laid out under the output directory.
+    pub layout: OutputLayout,
+    /// Writes `MANIFEST_SIDECAR` to the output directory, a repack index over the output paths
+    /// relative to it rather than the logical paths. Files skipped by the overwrite policy are
+    /// listed too, under their archive names. Paths renamed by `fix_extensions` are listed with
+    /// their corrected extensions.
+    pub write_manifest: bool,
+    /// Whether names Windows can't create are rewritten with `sanitize_windows_path`. Renames
+    /// are listed in `ExtractStats::renamed`; the repack index keeps the archived names. Only
+    /// the first of several names sanitizing to one path is extracted.
+    pub sanitize_names: SanitizeNames,
+}
+
+/// Output path layout for `MetaFile::extract_many_with_options` and `extract_with_options`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum OutputLayout {
+    /// Mirrors the archive's directories.
+    #[default]
+    Nested,
+    /// Writes every file straight into the output directory as
+    /// `<package_id><separator><file_name>`. When several records of a run flatten to the same
+    /// name each gets `<separator><hash>` (8 hex digits) before its extension, so the names
+    /// don't depend on the order records are extracted in. A single `extract_with_options`
+    /// can't see other records and never adds the hash.
+    Flat { separator: String },
+}
+
+/// Scheduling of reads during `MetaFile::extract_many`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ExtractOrder {
+    /// Packages are extracted in parallel and each package's records are read sequentially
+    /// by ascending offset, which keeps seeking down on spinning disks and network shares.
+    #[default]
+    ByPackage,
+    /// Records are extracted in parallel in meta table order.
+    Table,
+}
+
+/// Orderings for `MetaFile::sort_by`. Sorting is stable so ties keep their current order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetaSortKey {
+    /// Ascending file index then package and offset, the order the meta table is parsed in.
+    FileId,
+    /// Ascending package then offset within it, for sequential package reads.
+    Package,
+    /// Largest decoded size first, for reporting.
+    SizeDescending,
+}
+
+/// Policy for output files that already exist. Skipped records aren't read from their package.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Overwrite {
+    #[default]
+    Always,
+    Never,
+    /// Rewrites the file unless its size already matches what the record decodes to at the
+    /// requested level, for cheap incremental re-extraction.
+    IfDifferentSize,
+}
+
+/// Summary of an `extract_many` run. Failed records aren't counted.
+#[derive(Debug)]
+pub struct ExtractStats {
+    /// Files written.
+    pub files: usize,
+    /// Existing files left in place by the overwrite policy. They're still listed in the
+    /// repack index.
+    pub skipped: usize,
+    /// Bytes written across all files.
+    pub bytes: u64,
+    /// Wall time of the run from collecting targets through the last file written.
+    pub elapsed: std::time::Duration,
+    /// The hash and error of every record that couldn't be extracted.
+    pub failures: Vec<(u32, PadError)>,
+    /// Set when `extract_many_cancellable` was cancelled before every record was attempted.
+    pub cancelled: bool,
+    /// Requested logical paths no record resolved to, from `extract_paths`.
+    pub not_found: Vec<String>,
+    /// Output paths rewritten by `ExtractOptions::sanitize_names`, as (original, sanitized)
+    /// relative to the output directory.
+    pub renamed: Vec<(PathBuf, PathBuf)>,
+    /// Records skipped because an earlier record in meta table order has the same output path,
+    /// as (output path relative to the output directory, hash). Duplicate logical paths and
+    /// names that collide once lowercased or sanitized end up here.
+    pub collisions: Vec<(PathBuf, u32)>,
+}
+
+// Output paths from `MetaFile::output_targets`, with the records that have none.
+struct OutputTargets<'a> {
+    targets: Vec<(PathBuf, &'a MetaRecord)>,
+    unresolved: Vec<(&'a MetaRecord, PadError)>,
+    renamed: Vec<(PathBuf, PathBuf)>,
+    collisions: Vec<(PathBuf, u32)>,
+}
+
+/// An output file `MetaFile::extract` would write, from `MetaFile::plan`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedFile {
+    pub out_path: PathBuf,
+    pub sz_original: u32,
+    pub package_id: u32,
+}
+
+/// A record shown with its logical path and readable sizes, from `MetaFile::display`. The
+/// record's own `Debug` keeps the raw fields.
+#[derive(Clone, Copy)]
+pub struct RecordDisplay<'a> {
+    meta: &'a MetaFile,
+    record: &'a MetaRecord,
+}
+
+impl std::fmt::Display for RecordDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let record = self.record;
+        match self.meta.logical_path_string(record) {
+            Ok(logical_path) => write!(f, "{}", logical_path)?,
+            Err(_) => write!(
+                f,
+                "<unresolved path {} file {}>",
+                record.path_id, record.file_id
+            )?,
+        }
+        write!(
+            f,
+            " (package {}, {} stored, {} original, hash {:08x})",
+            record.package_id,
+            human_size(record.sz_compressed as u64),
+            human_size(record.sz_original as u64),
+            record.hash
+        )
+    }
+}
+
+/// A path or file name that didn't decode cleanly, from `MetaFile::decode_warnings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodeWarning {
+    /// `"path"` or `"file"`.
+    pub table: &'static str,
+    pub index: usize,
+    /// The name as decoded, holding U+FFFD where bytes weren't valid in the encoding.
+    pub name: PathBuf,
+}
+
+/// What a record's block header says, from `MetaFile::block_info`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Whether `read` would decompress the block at `ReadLevel::Decompress`.
+    pub compressed: bool,
+    /// Stored and original sizes from the quicklz header, or the record's when there is none.
+    pub sz_compressed: u32,
+    pub sz_original: u32,
+    /// First decrypted byte, the quicklz flag byte when compressed. `None` for an empty block.
+    pub first_byte: Option<u8>,
+}
+
+/// Totals over the records of one package, from `MetaFile::package_stats`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageStats {
+    pub package_id: u32,
+    pub file_count: usize,
+    pub total_compressed: u64,
+    pub total_original: u64,
+}
+
+/// Compression across the current selection from its size fields, from
+/// `MetaFile::compression_report`. Ratios are stored size over original size, so lower is
+/// better and a ratio of 1 or more means the record wasn't made any smaller.
+#[derive(Debug, Clone, Default)]
+pub struct CompressionReport {
+    pub record_count: usize,
+    pub total_compressed: u64,
+    pub total_original: u64,
+    /// `total_compressed / total_original`, 0 for an empty selection.
+    pub overall_ratio: f64,
+    /// Mean of the per-record ratios of records with a nonzero original size.
+    pub mean_ratio: f64,
+    /// Records stored no smaller than their original size, i.e. left uncompressed.
+    pub stored_count: usize,
+    /// The record with the lowest ratio, earliest in meta table order on ties.
+    pub best: Option<MetaRecord>,
+    /// The record with the highest ratio, earliest in meta table order on ties.
+    pub worst: Option<MetaRecord>,
+}
+
+/// Matching options for `MetaFile::filter_with`, `MetaFile::filter_by_file_with`,
+/// `MetaFile::filter_by_path_with` and their `filter_out_*_with`, `count_matching_*_with` and
+/// `MetaView` counterparts.
+#[derive(Debug, Default)]
+pub struct FilterOptions {
+    /// Matches without regard to case, as if the pattern started with `(?i)`. Hangul has no
+    /// case so for EUC_KR decoded names this only folds the ASCII letters (and the few Greek
+    /// and Cyrillic letters EUC_KR can hold).
+    pub case_insensitive: bool,
+}
+
+impl FilterOptions {
+    pub(crate) fn regex(&self, pattern: &str) -> Result<regex::Regex, PadError> {
+        Ok(regex::RegexBuilder::new(pattern)
+            .case_insensitive(self.case_insensitive)
+            .build()?)
+    }
+}
+
+/// Meta table sizes around a single filter pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FilterStats {
+    pub before: usize,
+    pub after: usize,
+    pub removed: usize,
+}
+
+impl FilterStats {
+    pub(crate) fn new(before: usize, after: usize) -> FilterStats {
+        FilterStats {
+            before,
+            after,
+            removed: before - after,
+        }
+    }
+}
+
+// Records claiming a larger original size than this are treated as corrupt rather than
+// attempting the allocation.
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 2 * 1024 * 1024 * 1024;
+
+// File extensions whose blocks the client stores without ICE encryption, compared ignoring
+// ASCII case. `MetaFile::unencrypted_extensions` starts out as this list.
+pub const DEFAULT_UNENCRYPTED_EXTENSIONS: &[&str] = &["dbss"];
+
+// Whether blocks of `file_name` are stored unencrypted, going by its extension ignoring ASCII
+// case. `extensions` are given without the leading dot.
+pub fn is_unencrypted_name<S: AsRef<str>>(file_name: &Path, extensions: &[S]) -> bool {
+    match file_name.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => extensions
+            .iter()
+            .any(|unencrypted| ext.eq_ignore_ascii_case(unencrypted.as_ref())),
+        None => false,
+    }
+}
+
+/// Byte ranges of the meta file's blocks, each following its u32 record or byte count, from
+/// `MetaFile::block_offsets`. The path and file blocks are still encrypted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockOffsets {
+    pub packages: std::ops::Range<usize>,
+    pub metas: std::ops::Range<usize>,
+    pub paths: std::ops::Range<usize>,
+    pub files: std::ops::Range<usize>,
+}
+
 enum BlockType {
     Packages,
     Metas,
@@ -25,8 +361,8 @@ enum BlockType {
 
 fn block_range(
     block: BlockType,
-    reader: &mut Cursor<&mut Vec<u8>>,
-) -> Result<std::ops::Range<usize>, Box<dyn Error>> {
+    reader: &mut Cursor<&[u8]>,
+) -> Result<std::ops::Range<usize>, PadError> {
     let count = reader.read_u32::<LittleEndian>()? as u64;
     let start = reader.position();
     let end = match block {
@@ -35,6 +371,9 @@ fn block_range(
         BlockType::Paths => start + count,
         BlockType::Files => start + count,
     };
+    if end > reader.get_ref().len() as u64 {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
     reader.set_position(end);
     Ok(std::ops::Range {
         start: start as usize,
@@ -42,64 +381,267 @@ fn block_range(
     })
 }
 
+// The Korean client's encoding for path and file names, used unless one is given.
+pub const DEFAULT_ENCODING: &Encoding = encoding_rs::EUC_KR;
+
+// The file `ExtractOptions::write_manifest` writes in the output directory.
+pub const MANIFEST_SIDECAR: &str = ".pad-manifest.json";
+
+// The meta file name `MetaFile::new` looks for in the root directory.
+pub const DEFAULT_META_FILE: &str = "pad00000.meta";
+
+pub(crate) fn read_meta(meta_path: &Path) -> Result<Vec<u8>, PadError> {
+    std::fs::read(meta_path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => PadError::MissingMeta(meta_path.to_path_buf()),
+        _ => PadError::Io(e),
+    })
+}
+
 #[derive(Debug)]
 pub struct MetaFile {
     pub ice: Ice,
+    // The key `ice` was built from, read through `key` and changed only by `rekey` so the two
+    // stay in step.
+    key: Vec<u8>,
     pub root: PathBuf,
     pub version: u32,
-    pub package_table: Vec<PackageRecord>,
+    // The package, path and file tables are shared between a meta and its `clone_filtered`
+    // copies; changing one through `Arc::make_mut` leaves the others as they were.
+    pub package_table: std::sync::Arc<Vec<PackageRecord>>,
     pub meta_table: Vec<MetaRecord>,
-    pub path_table: Vec<PathRecord>,
-    pub file_table: Vec<PathBuf>,
+    // The meta table as parsed, set aside by the first filter so `clear_filters` can restore it.
+    pub unfiltered_meta_table: Option<Vec<MetaRecord>>,
+    // Meta table positions by record hash, built by `find_by_hash` and dropped by every method
+    // that changes `meta_table`.
+    hash_index: std::sync::OnceLock<std::collections::HashMap<u32, usize>>,
+    pub path_table: std::sync::Arc<Vec<PathRecord>>,
+    pub file_table: std::sync::Arc<Vec<PathBuf>>,
+    // Path and file table indices whose names the decoder reported errors for while parsing.
+    undecodable_paths: Vec<usize>,
+    undecodable_files: Vec<usize>,
+    pub max_decompressed_size: u64,
+    // Extensions of files whose blocks are read without decrypting, `DEFAULT_UNENCRYPTED_EXTENSIONS`
+    // unless changed. Push to it for other unencrypted file types.
+    pub unencrypted_extensions: Vec<String>,
+    pub share_packages: bool,
+    pub backend: ReadBackend,
+    pub open_packages: OpenPackages,
+    pub mapped_packages: MappedPackages,
+    // Pool every parallel pass runs on, block decryption included, the global rayon pool if
+    // `None`.
+    pub thread_pool: Option<std::sync::Arc<rayon::ThreadPool>>,
+    // Packages are read through this instead of from `root` when set, whatever `backend` is.
+    pub source: Option<std::sync::Arc<dyn PackageSource>>,
 }
 
 impl MetaFile {
-    // The path table is organized such that each entry is a bucket of file indices.
-    // The raw data is organized for hash lookups, but this library organizes it for
-    // efficient filtering and extraction directly using the path table bucket indices
-    // on the meta table records.
-    // In order to filter by bucket indices the meta table needs to be sorted by file index.
-    pub fn new(buf: &mut Vec<u8>, key: &[u8; 8]) -> Result<Self, Box<dyn Error>> {
-        let ice = Ice::new(0, key);
-        let root = PathBuf::new();
+    // `root` is the directory holding `pad00000.meta` and the `.paz` packages, not the path of
+    // the meta file itself.
+    pub fn new(root: &Path, key: &[u8]) -> Result<Self, PadError> {
+        Self::new_with_meta(root, Path::new(DEFAULT_META_FILE), key)
+    }
+
+    // Like `new` for installs whose meta isn't `pad00000.meta`. A relative `meta_file` is
+    // resolved against `root`; an absolute one is used as is and packages still come from `root`.
+    pub fn new_with_meta(root: &Path, meta_file: &Path, key: &[u8]) -> Result<Self, PadError> {
+        let buf = read_meta(&root.join(meta_file))?;
+        Self::from_bytes(buf, root.to_path_buf(), key)
+    }
+
+    // Parses meta file contents already in memory. `root` is only used afterwards, to locate
+    // the `.paz` packages.
+    pub fn from_bytes(bytes: Vec<u8>, root: PathBuf, key: &[u8]) -> Result<Self, PadError> {
+        Self::from_bytes_with_encoding(bytes, root, key, DEFAULT_ENCODING)
+    }
+
+    // Like `new` for metas whose path and file names aren't EUC_KR, as in other regions' clients.
+    pub fn new_with_encoding(
+        root: &Path,
+        key: &[u8],
+        encoding: &'static Encoding,
+    ) -> Result<Self, PadError> {
+        let buf = read_meta(&root.join(DEFAULT_META_FILE))?;
+        Self::from_bytes_with_encoding(buf, root.to_path_buf(), key, encoding)
+    }
 
-        let mut reader = Cursor::new(&mut *buf);
+    pub fn from_bytes_with_encoding(
+        mut bytes: Vec<u8>,
+        root: PathBuf,
+        key: &[u8],
+        encoding: &'static Encoding,
+    ) -> Result<Self, PadError> {
+        let mut meta = Self::from_buffer_with_encoding(&mut bytes, key, encoding)?;
+        meta.root = root;
+        Ok(meta)
+    }
 
-        let version = reader.read_u32::<LittleEndian>().unwrap();
+    // Parses each of `metas` (resolved as in `new_with_meta`) and concatenates their tables.
+    // Path and file ids, and the path buckets' file ranges, are offset by the lengths of the
+    // tables before them so they index the merged tables. Package ids aren't indices, they name
+    // the `.paz` files under `root`, so they're kept and repeated packages are listed once.
+    pub fn new_many(root: &Path, metas: &[&Path], key: &[u8]) -> Result<Self, PadError> {
+        let mut merged: Option<Self> = None;
+        for meta_file in metas {
+            let meta = Self::new_with_meta(root, meta_file, key)?;
+            match merged.as_mut() {
+                None => merged = Some(meta),
+                Some(merged) => merged.append(meta),
+            }
+        }
+        merged.ok_or_else(|| PadError::MissingMeta(root.to_path_buf()))
+    }
 
-        let range = block_range(BlockType::Packages, &mut reader)?;
-        let package_table = PackageRecord::many_from_le_bytes(&reader.get_ref()[range]);
+    fn append(&mut self, other: MetaFile) {
+        let path_offset = self.path_table.len() as u32;
+        let file_offset = self.file_table.len() as u32;
+        let known: std::collections::HashSet<u32> =
+            self.package_table.iter().map(|pr| pr.id).collect();
+        std::sync::Arc::make_mut(&mut self.package_table).extend(
+            std::sync::Arc::unwrap_or_clone(other.package_table)
+                .into_iter()
+                .filter(|pr| !known.contains(&pr.id)),
+        );
+        self.meta_table
+            .extend(other.meta_table.into_iter().map(|mr| MetaRecord {
+                path_id: mr.path_id + path_offset,
+                file_id: mr.file_id + file_offset,
+                ..mr
+            }));
+        std::sync::Arc::make_mut(&mut self.path_table).extend(
+            std::sync::Arc::unwrap_or_clone(other.path_table)
+                .into_iter()
+                .map(|pr| PathRecord {
+                    file_range: pr.file_range.start + file_offset as usize
+                        ..pr.file_range.end + file_offset as usize,
+                    ..pr
+                }),
+        );
+        std::sync::Arc::make_mut(&mut self.file_table)
+            .extend(std::sync::Arc::unwrap_or_clone(other.file_table));
+        self.undecodable_paths.extend(
+            other
+                .undecodable_paths
+                .iter()
+                .map(|i| i + path_offset as usize),
+        );
+        self.undecodable_files.extend(
+            other
+                .undecodable_files
+                .iter()
+                .map(|i| i + file_offset as usize),
+        );
+    }
 
-        let range = block_range(BlockType::Metas, &mut reader)?;
-        let mut meta_table = MetaRecord::many_from_le_bytes(&reader.get_ref()[range]);
-        meta_table.par_sort_by_key(|x| x.file_id);
+    #[deprecated(note = "use `MetaFile::new`")]
+    pub fn new_from_path(root: &Path, key: &[u8]) -> Result<Self, PadError> {
+        Self::new(root, key)
+    }
 
-        let range = block_range(BlockType::Paths, &mut reader)?;
-        let path_table =
-            PathRecord::many_from_encrypted_le_bytes(&mut reader.get_mut()[range], &ice);
+    // The path table is organized such that each entry is a bucket of file indices.
+    // The raw data is organized for hash lookups, but this library sorts the meta table
+    // by file index so each bucket's records sit together in path table order.
+    // The buffer holds the raw meta file contents; `root` is left empty.
+    pub fn from_buffer(buf: &mut [u8], key: &[u8]) -> Result<Self, PadError> {
+        Self::from_buffer_with_encoding(buf, key, DEFAULT_ENCODING)
+    }
 
-        let range = block_range(BlockType::Files, &mut reader)?;
-        let file_table =
-            FileRecord::many_from_encrypted_le_bytes(&mut reader.get_mut()[range], &ice);
+    fn from_buffer_with_encoding(
+        buf: &mut [u8],
+        key: &[u8],
+        encoding: &'static Encoding,
+    ) -> Result<Self, PadError> {
+        let mut meta_table = Vec::new();
+        let mut meta_file = Self::parse(buf, key, encoding, |bytes| {
+            meta_table = MetaRecord::many_from_le_bytes(bytes);
+        })?;
+        log::info!("parsed {} meta records", meta_table.len());
+        meta_table.par_sort_by_key(file_id_order);
+        meta_file.meta_table = meta_table;
+        Ok(meta_file)
+    }
+
+    // Hands each meta record to `on_record` in on-disk order instead of collecting the meta
+    // table, which is left empty. The package, path and file tables are parsed as usual.
+    pub fn parse_streaming(
+        root: &Path,
+        key: &[u8],
+        mut on_record: impl FnMut(MetaRecord),
+    ) -> Result<Self, PadError> {
+        let mut buf = read_meta(&root.join(DEFAULT_META_FILE))?;
+        let mut meta = Self::parse(&mut buf, key, DEFAULT_ENCODING, |bytes| {
+            bytes
+                .chunks_exact(28)
+                .for_each(|chunk| on_record(MetaRecord::from_le_bytes(chunk.try_into().unwrap())));
+        })?;
+        meta.root = root.to_path_buf();
+        Ok(meta)
+    }
+
+    fn parse(
+        buf: &mut [u8],
+        key: &[u8],
+        encoding: &'static Encoding,
+        on_metas: impl FnOnce(&[u8]),
+    ) -> Result<Self, PadError> {
+        let ice = ice_for_key(key)?;
+        let root = PathBuf::new();
+
+        let offsets = Self::block_offsets(buf)?;
+        log::debug!("meta blocks at {:?}", offsets);
+        let version = u32::from_le_bytes(buf[..4].try_into().unwrap());
+        let package_table = PackageRecord::many_from_le_bytes(&buf[offsets.packages]);
+        on_metas(&buf[offsets.metas]);
+        let (path_table, undecodable_paths) =
+            PathRecord::many_from_encrypted_le_bytes(&mut buf[offsets.paths], &ice, encoding);
+        let (file_table, undecodable_files) =
+            FileRecord::many_from_encrypted_le_bytes(&mut buf[offsets.files], &ice, encoding);
+        log::info!(
+            "parsed meta version {}: {} packages, {} paths, {} files",
+            version,
+            package_table.len(),
+            path_table.len(),
+            file_table.len()
+        );
 
         let meta_file = MetaFile {
             ice,
+            key: key.to_vec(),
             root,
             version,
-            package_table,
-            meta_table,
-            path_table,
-            file_table,
+            package_table: package_table.into(),
+            meta_table: Vec::new(),
+            unfiltered_meta_table: None,
+            hash_index: Default::default(),
+            path_table: path_table.into(),
+            file_table: file_table.into(),
+            undecodable_paths,
+            undecodable_files,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            unencrypted_extensions: DEFAULT_UNENCRYPTED_EXTENSIONS
+                .iter()
+                .map(|ext| ext.to_string())
+                .collect(),
+            share_packages: true,
+            backend: ReadBackend::default(),
+            open_packages: OpenPackages::default(),
+            mapped_packages: MappedPackages::default(),
+            thread_pool: None,
+            source: None,
         };
         Ok(meta_file)
     }
 
-    pub fn new_from_path(root: &Path, key: &[u8; 8]) -> Result<Self, Box<dyn Error>> {
-        let metafile = PathBuf::from("pad00000.meta");
-        let mut buf = std::fs::read(root.join(metafile))?;
-        let mut meta = Self::new(&mut buf, key)?;
-        meta.root = root.to_path_buf();
-        Ok(meta)
+    // Locates the blocks of raw meta file contents without decrypting anything, for inspecting
+    // new archive versions or writing other parsers. Fails if a block runs past the end.
+    pub fn block_offsets(meta_bytes: &[u8]) -> Result<BlockOffsets, PadError> {
+        let mut reader = Cursor::new(meta_bytes);
+        reader.read_u32::<LittleEndian>()?;
+        Ok(BlockOffsets {
+            packages: block_range(BlockType::Packages, &mut reader)?,
+            metas: block_range(BlockType::Metas, &mut reader)?,
+            paths: block_range(BlockType::Paths, &mut reader)?,
+            files: block_range(BlockType::Files, &mut reader)?,
+        })
     }
 
     pub fn extract(
@@ -107,93 +649,1487 @@ impl MetaFile {
         record: &MetaRecord,
         level: &ReadLevel,
         out_path: &Path,
-    ) -> Result<(), Box<dyn Error>> {
-        let file_path = self.path_table[record.path_id as usize].path.clone();
-        let file_name = &self.file_table[record.file_id as usize];
-        let out_path = &out_path.join(file_path).join(file_name);
-        let mut f = std::fs::File::create(out_path)?;
-        let buf = &self.read(record, level)?;
-        f.write_all(buf)?;
+    ) -> Result<(), PadError> {
+        self.extract_with_options(record, level, out_path, &ExtractOptions::default())?;
         Ok(())
     }
 
-    pub fn extract_many(&self, level: &ReadLevel, out_path: &Path) -> Result<(), Box<dyn Error>> {
-        self.meta_table
-            .iter()
-            .map(|mr| self.path_table[mr.path_id as usize].path.clone())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .for_each(|p| std::fs::create_dir_all(out_path.join(p)).expect("create dir failed"));
-        self.meta_table.par_iter().for_each(|mr| {
-            if let Err(e) = self.extract(mr, level, out_path) {
-                let path = self.path_table[mr.path_id as usize].path.clone();
-                let file = &self.file_table[mr.file_id as usize];
-                let out_path = path.join(file);
-                println!(
-                    "Failed {}\n metarecord: {:?}\n with error: {}\n",
-                    out_path.display(),
+    // Like `extract`, returning false when `options.overwrite` left an existing file in place.
+    pub fn extract_with_options(
+        &self,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        out_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<bool, PadError> {
+        let rel_path = self.output_path(record, options)?;
+        let (_, written) = self.extract_to(record, level, out_path, &rel_path, options)?;
+        Ok(written.is_some())
+    }
+
+    // Where `extract_with_options` writes `record`, relative to the output directory.
+    pub(crate) fn output_path(
+        &self,
+        record: &MetaRecord,
+        options: &ExtractOptions,
+    ) -> Result<PathBuf, PadError> {
+        let mut output = self.output_targets(&[record], options);
+        match output.unresolved.pop() {
+            Some((_, e)) => Err(e),
+            None => Ok(output.targets.swap_remove(0).0),
+        }
+    }
+
+    // Where each of `records` is written under `options`, relative to the output directory:
+    // lowercased, flattened and sanitized as the options ask, then with records whose output
+    // path an earlier record already took dropped. Single, bulk and async extraction and `plan`
+    // all lay out their outputs through here so a record lands on the same path either way.
+    fn output_targets<'a>(
+        &self,
+        records: &[&'a MetaRecord],
+        options: &ExtractOptions,
+    ) -> OutputTargets<'a> {
+        let resolved: Vec<(&MetaRecord, Result<PathBuf, PadError>)> = records
+            .par_iter()
+            .map(|&mr| (mr, self.logical_path(mr)))
+            .collect();
+        let mut output = OutputTargets {
+            targets: Vec::with_capacity(resolved.len()),
+            unresolved: Vec::new(),
+            renamed: Vec::new(),
+            collisions: Vec::new(),
+        };
+        for (mr, logical_path) in resolved {
+            match logical_path {
+                Ok(logical_path) if options.lowercase_paths => output.targets.push((
+                    PathBuf::from(logical_path.to_string_lossy().to_lowercase()),
                     mr,
-                    e
-                );
+                )),
+                Ok(logical_path) => output.targets.push((logical_path, mr)),
+                Err(e) => output.unresolved.push((mr, e)),
+            }
+        }
+        if let OutputLayout::Flat { separator } = &options.layout {
+            output.targets = flatten(output.targets, separator);
+        }
+        if options.sanitize_names.applies() {
+            for (rel_path, _) in &mut output.targets {
+                let sanitized = sanitize_windows_path(rel_path);
+                if sanitized != *rel_path {
+                    log::debug!(
+                        "sanitized {} to {}",
+                        rel_path.display(),
+                        sanitized.display()
+                    );
+                    output
+                        .renamed
+                        .push((std::mem::replace(rel_path, sanitized.clone()), sanitized));
+                }
             }
+        }
+        self.drop_collisions(&mut output);
+        output
+    }
+
+    // The files `extract` would write for the current selection under `out_path`, without
+    // reading packages or touching the disk. Records `extract` refuses, for an unsafe path or
+    // an out of range path or file id, are left out.
+    pub fn plan(&self, out_path: &Path) -> Vec<PlannedFile> {
+        self.plan_with_options(out_path, &ExtractOptions::default())
+    }
+
+    // The files `extract_many_with_options` would write with `options`, in meta table order.
+    // Colliding records it would skip are left out too.
+    pub fn plan_with_options(&self, out_path: &Path, options: &ExtractOptions) -> Vec<PlannedFile> {
+        let records: Vec<&MetaRecord> = self.meta_table.iter().collect();
+        self.install(|| self.output_targets(&records, options))
+            .targets
+            .into_iter()
+            .filter_map(|(rel_path, mr)| {
+                Some(PlannedFile {
+                    out_path: out_path.join(checked_relative(&rel_path).ok()?),
+                    sz_original: mr.sz_original,
+                    package_id: mr.package_id,
+                })
+            })
+            .collect()
+    }
+
+    // The record's path in the archive, its directory joined with its file name. Every path
+    // derived from a record goes through here so a corrupt id is an error instead of a panic.
+    pub fn logical_path(&self, record: &MetaRecord) -> Result<PathBuf, PadError> {
+        Ok(self.directory(record)?.join(self.file_name(record)?))
+    }
+
+    // The record's directory path from the path table, with its trailing `/`.
+    pub fn directory(&self, record: &MetaRecord) -> Result<&Path, PadError> {
+        self.path_table
+            .get(record.path_id as usize)
+            .map(|pr| pr.path.as_path())
+            .ok_or(PadError::IndexOutOfRange {
+                table: "path",
+                index: record.path_id as usize,
+            })
+    }
+
+    // The record's file name from the file table.
+    pub fn file_name(&self, record: &MetaRecord) -> Result<&Path, PadError> {
+        self.file_table
+            .get(record.file_id as usize)
+            .map(|file| file.as_path())
+            .ok_or(PadError::IndexOutOfRange {
+                table: "file",
+                index: record.file_id as usize,
+            })
+    }
+
+    // Writes `record` to `rel_path` under `out_path`, returning the path relative to `out_path`
+    // it ended up at, which `fix_extensions` may have changed, with the bytes written or `None`
+    // when the overwrite policy kept the existing file.
+    fn extract_to(
+        &self,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        out_path: &Path,
+        rel_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<(PathBuf, Option<u64>), PadError> {
+        let out_file = &out_path.join(checked_relative(rel_path)?);
+        if self.keep_existing(out_file, record, level, options.overwrite) {
+            return Ok((rel_path.to_path_buf(), None));
+        }
+        if !options.fix_extensions {
+            let mut w = create_output(out_file)?;
+            let written = self.extract_to_writer(record, level, &mut w)?;
+            w.flush()?;
+            return Ok((rel_path.to_path_buf(), Some(written)));
+        }
+        let buf = &self.read(record, level)?;
+        let mut rel_path = rel_path.to_path_buf();
+        if let Some(ext) = sniff::sniff_extension(buf, &options.extra_signatures) {
+            let current = rel_path.extension().and_then(|e| e.to_str());
+            if !matches!(current, Some(c) if c.eq_ignore_ascii_case(ext)) {
+                let fixed = rel_path.with_extension(ext);
+                log::info!("renamed {} -> {}", rel_path.display(), fixed.display());
+                rel_path = fixed;
+            }
+        }
+        let mut w = create_output(&out_path.join(&rel_path))?;
+        w.write_all(buf)?;
+        w.flush()?;
+        Ok((rel_path, Some(buf.len() as u64)))
+    }
+
+    // Decided from the archive name, so with `fix_extensions` a renamed output isn't seen.
+    fn keep_existing(
+        &self,
+        out_file: &Path,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        overwrite: Overwrite,
+    ) -> bool {
+        match overwrite {
+            Overwrite::Always => false,
+            Overwrite::Never => out_file.exists(),
+            Overwrite::IfDifferentSize => match std::fs::metadata(out_file) {
+                Ok(m) => m.len() == self.output_size(record, level),
+                Err(_) => false,
+            },
+        }
+    }
+
+    // Runs the same read pipeline as `extract` but hands the decoded bytes to `w`, returning
+    // the number of bytes written.
+    pub fn extract_to_writer(
+        &self,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        w: &mut dyn Write,
+    ) -> Result<u64, PadError> {
+        let buf = self.read(record, level)?;
+        w.write_all(&buf)?;
+        Ok(buf.len() as u64)
+    }
+
+    // Extracts the single file at `logical_path` (e.g. `gamecommondata/binary/de.bss`), returning
+    // false when the current meta table has no such file.
+    pub fn extract_one(
+        &self,
+        logical_path: &str,
+        level: &ReadLevel,
+        out_path: &Path,
+    ) -> Result<bool, PadError> {
+        let logical_path = logical_path.replace('\\', "/");
+        let (dir, name) = match logical_path.rfind('/') {
+            Some(i) => logical_path.split_at(i + 1),
+            None => ("", logical_path.as_str()),
+        };
+        let found = self.install(|| {
+            self.meta_table.par_iter().find_first(|mr| {
+                matches!(self.directory(mr), Ok(d) if d.to_str() == Some(dir))
+                    && matches!(self.file_name(mr), Ok(f) if f.to_str() == Some(name))
+            })
         });
-        Ok(())
+        match found {
+            Some(record) => self.extract(record, level, out_path).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    pub fn extract_many(
+        &self,
+        level: &ReadLevel,
+        out_path: &Path,
+    ) -> Result<ExtractStats, PadError> {
+        self.extract_many_with_options(level, out_path, &ExtractOptions::default())
     }
 
-    pub fn filter_by_file(&mut self, pattern: &str) -> Result<(), Box<dyn Error>> {
-        let re = regex::Regex::new(pattern).unwrap();
-        self.meta_table = self
+    pub fn extract_many_with_options(
+        &self,
+        level: &ReadLevel,
+        out_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<ExtractStats, PadError> {
+        let cancel = AtomicBool::new(false);
+        let records: Vec<_> = self.meta_table.iter().collect();
+        self.extract_many_impl(&records, level, out_path, options, &cancel, |_, _, _| ())
+    }
+
+    // Calls `on_done` with the running count of finished records (extracted or failed) after
+    // each one completes, e.g. to drive a progress bar. It's called from the rayon workers.
+    pub fn extract_many_with_progress(
+        &self,
+        level: &ReadLevel,
+        out_path: &Path,
+        on_done: impl Fn(usize) + Sync,
+    ) -> Result<ExtractStats, PadError> {
+        let cancel = AtomicBool::new(false);
+        let records: Vec<_> = self.meta_table.iter().collect();
+        self.extract_many_impl(
+            &records,
+            level,
+            out_path,
+            &ExtractOptions::default(),
+            &cancel,
+            |done, _, _| on_done(done),
+        )
+    }
+
+    // Stops starting new records once `cancel` is set, e.g. from a UI thread, and returns with
+    // `ExtractStats::cancelled` set. Files already written are left in place.
+    pub fn extract_many_cancellable(
+        &self,
+        level: &ReadLevel,
+        out_path: &Path,
+        cancel: &AtomicBool,
+    ) -> Result<ExtractStats, PadError> {
+        let records: Vec<_> = self.meta_table.iter().collect();
+        let options = ExtractOptions::default();
+        self.extract_many_impl(&records, level, out_path, &options, cancel, |_, _, _| ())
+    }
+
+    // Extracts the selection, skipping records whose hash is already listed in `journal` and
+    // appending each record extracted (or skipped by `overwrite`) once it's complete. The
+    // journal holds one hex hash per line; a line cut short by a crash is ignored so its record
+    // is extracted again. The journal is synced before returning, and deleting it starts over.
+    pub fn extract_many_resumable(
+        &self,
+        level: &ReadLevel,
+        out_path: &Path,
+        journal: &Path,
+    ) -> Result<ExtractStats, PadError> {
+        let (completed, complete_len) = read_journal(journal)?;
+        let records: Vec<_> = self
             .meta_table
-            .par_iter()
-            .filter(|x| re.is_match(self.file_table[x.file_id as usize].to_str().unwrap()))
-            .cloned()
+            .iter()
+            .filter(|mr| !completed.contains(&mr.hash))
             .collect();
-        Ok(())
+        let journal = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(journal)?;
+        // Drops a torn last line so the next hash starts a line of its own.
+        journal.set_len(complete_len)?;
+        let journal = std::sync::Mutex::new(journal);
+        let cancel = AtomicBool::new(false);
+        let options = ExtractOptions::default();
+        let stats =
+            self.extract_many_impl(&records, level, out_path, &options, &cancel, |_, mr, ok| {
+                if ok {
+                    // A line that fails to write only means its record is extracted again.
+                    let line = format!("{:08x}\n", mr.hash);
+                    let _ = journal.lock().unwrap().write_all(line.as_bytes());
+                }
+            })?;
+        journal.into_inner().unwrap().sync_data()?;
+        Ok(stats)
     }
 
-    pub fn filter_by_path(&mut self, re_pat: &str) -> Result<(), Box<dyn Error>> {
-        let re = regex::Regex::new(re_pat).unwrap();
-        self.meta_table = self
-            .path_table
+    // Extracts the records at the given logical paths, as `list` spells them with `/` separators,
+    // e.g. a selection saved from a manifest. Every record a path resolves to is extracted and
+    // paths matching no record are listed in `ExtractStats::not_found`.
+    pub fn extract_paths(
+        &self,
+        paths: &[&str],
+        level: &ReadLevel,
+        out_path: &Path,
+    ) -> Result<ExtractStats, PadError> {
+        let wanted: std::collections::HashSet<&str> = paths.iter().copied().collect();
+        let mut found = std::collections::HashSet::new();
+        let mut records = Vec::new();
+        for mr in &self.meta_table {
+            let Ok(logical_path) = self.logical_path_string(mr) else {
+                continue;
+            };
+            if let Some(path) = wanted.get(logical_path.as_str()) {
+                found.insert(*path);
+                records.push(mr);
+            }
+        }
+        let not_found: Vec<String> = paths
             .iter()
-            .filter(|x| re.is_match(x.path.to_str().unwrap()))
-            .flat_map(|pr| self.meta_table[pr.file_range.clone()].to_vec())
+            .filter(|path| !found.contains(*path))
+            .map(|path| path.to_string())
             .collect();
+        let cancel = AtomicBool::new(false);
+        let options = ExtractOptions::default();
+        let mut stats =
+            self.extract_many_impl(&records, level, out_path, &options, &cancel, |_, _, _| ())?;
+        stats.not_found = not_found;
+        Ok(stats)
+    }
+
+    // `on_done` is called from the workers with the running count of finished records, the
+    // record and whether it was extracted or skipped rather than failed.
+    pub(crate) fn extract_many_impl(
+        &self,
+        records: &[&MetaRecord],
+        level: &ReadLevel,
+        out_path: &Path,
+        options: &ExtractOptions,
+        cancel: &AtomicBool,
+        on_done: impl Fn(usize, &MetaRecord, bool) + Sync,
+    ) -> Result<ExtractStats, PadError> {
+        let on_done = &on_done;
+        self.install(|| {
+            self.extract_many_in_pool(records, level, out_path, options, cancel, on_done)
+        })
+    }
+
+    fn extract_many_in_pool<'a>(
+        &self,
+        records: &[&'a MetaRecord],
+        level: &ReadLevel,
+        out_path: &Path,
+        options: &ExtractOptions,
+        cancel: &AtomicBool,
+        on_done: impl Fn(usize, &MetaRecord, bool) + Sync,
+    ) -> Result<ExtractStats, PadError> {
+        let started = std::time::Instant::now();
+        let done = AtomicUsize::new(0);
+        let bytes = AtomicU64::new(0);
+        let skipped = AtomicUsize::new(0);
+        let OutputTargets {
+            targets,
+            unresolved: unresolved_records,
+            renamed,
+            collisions,
+        } = self.output_targets(records, options);
+        // Records whose path or file id is out of range fail on their own like any other record.
+        let mut unresolved = Vec::with_capacity(unresolved_records.len());
+        for (mr, e) in unresolved_records {
+            log::warn!("failed {}: {} ({:?})", self.display(mr), e, mr);
+            on_done(done.fetch_add(1, Ordering::Relaxed) + 1, mr, false);
+            if options.fail_fast {
+                return Err(e);
+            }
+            unresolved.push((mr.hash, e));
+        }
+        // Creating each directory once up front saves the workers racing to create them. A
+        // failure here is left for `create_output` to report against the records it affects.
+        targets
+            .iter()
+            .filter(|(p, _)| checked_relative(p).is_ok())
+            .filter_map(|(p, _)| p.parent())
+            .collect::<std::collections::HashSet<_>>()
+            .into_iter()
+            .for_each(|p| {
+                let _ = std::fs::create_dir_all(out_path.join(p));
+            });
+        let failures = std::sync::Mutex::new(unresolved);
+        let aborted = AtomicBool::new(false);
+        // Set once a record is passed over for `cancel`, rather than when `cancel` is raised
+        // after the last record was started.
+        let cancelled = AtomicBool::new(false);
+        // The path each extracted record ended up at, for the manifest.
+        let extract_target = |(rel_path, mr): &(PathBuf, &'a MetaRecord)| {
+            if aborted.load(Ordering::Relaxed) {
+                return None;
+            }
+            if cancel.load(Ordering::Relaxed) {
+                cancelled.store(true, Ordering::Relaxed);
+                return None;
+            }
+            let result = self.extract_to(mr, level, out_path, rel_path, options);
+            on_done(done.fetch_add(1, Ordering::Relaxed) + 1, mr, result.is_ok());
+            match result {
+                Ok((final_path, Some(written))) => {
+                    bytes.fetch_add(written, Ordering::Relaxed);
+                    Some((final_path, *mr))
+                }
+                Ok((final_path, None)) => {
+                    skipped.fetch_add(1, Ordering::Relaxed);
+                    Some((final_path, *mr))
+                }
+                Err(e) => {
+                    log::warn!("failed {}: {} ({:?})", rel_path.display(), e, mr);
+                    if options.fail_fast {
+                        aborted.store(true, Ordering::Relaxed);
+                    }
+                    failures.lock().unwrap().push((mr.hash, e));
+                    None
+                }
+            }
+        };
+        let extracted: Vec<(PathBuf, &MetaRecord)> = match options.order {
+            ExtractOrder::ByPackage => {
+                let mut packages = std::collections::BTreeMap::<u32, Vec<_>>::new();
+                for target in &targets {
+                    packages
+                        .entry(target.1.package_id)
+                        .or_default()
+                        .push(target);
+                }
+                packages
+                    .into_par_iter()
+                    .flat_map_iter(|(package_id, mut package_targets)| {
+                        log::debug!(
+                            "extracting {} records from package {}",
+                            package_targets.len(),
+                            package_id
+                        );
+                        package_targets.sort_by_key(|(_, mr)| mr.package_offset);
+                        let extracted: Vec<_> = package_targets
+                            .into_iter()
+                            .filter_map(extract_target)
+                            .collect();
+                        log::debug!(
+                            "finished package {} with {} records extracted",
+                            package_id,
+                            extracted.len()
+                        );
+                        extracted
+                    })
+                    .collect()
+            }
+            ExtractOrder::Table => targets.par_iter().filter_map(extract_target).collect(),
+        };
+        let mut failures = failures.into_inner().unwrap();
+        if options.fail_fast && !failures.is_empty() {
+            return Err(failures.swap_remove(0).1);
+        }
+        let skipped = skipped.into_inner();
+        let stats = ExtractStats {
+            files: extracted.len() - skipped,
+            skipped,
+            bytes: bytes.into_inner(),
+            elapsed: started.elapsed(),
+            failures,
+            cancelled: cancelled.into_inner(),
+            not_found: Vec::new(),
+            renamed,
+            collisions,
+        };
+        log::info!(
+            "extracted {} files ({} bytes) in {:?}, {} skipped, {} failed",
+            stats.files,
+            stats.bytes,
+            stats.elapsed,
+            stats.skipped,
+            stats.failures.len()
+        );
+        if let Some(index_path) = &options.emit_repack_index {
+            let entries: Vec<_> = extracted
+                .iter()
+                .map(|(_, mr)| Ok((self.logical_path(mr)?, *mr)))
+                .collect::<Result<_, PadError>>()?;
+            repack::write_index(&out_path.join(index_path), &entries)?;
+        }
+        if options.write_manifest {
+            let entries: Vec<_> = extracted
+                .iter()
+                .map(|(rel_path, mr)| (rel_path.clone(), *mr))
+                .collect();
+            repack::write_index(&out_path.join(MANIFEST_SIDECAR), &entries)?;
+        }
+        Ok(stats)
+    }
+
+    // Decodes the whole current selection in parallel, returning each record's meta table index
+    // with its bytes or its error, in meta table order. A failed record doesn't stop the rest.
+    // Everything is held at once, as with `extract_all_to_memory`.
+    pub fn read_many(&self, level: &ReadLevel) -> Vec<(usize, Result<Vec<u8>, PadError>)> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .enumerate()
+                .map(|(i, mr)| (i, self.read(mr, level)))
+                .collect()
+        })
+    }
+
+    // Decodes the whole current selection into memory keyed by logical path, failing on the first
+    // record that can't be read. Everything is held at once so this is meant for a filtered
+    // selection, e.g. a server preloading a few asset folders, not for a full archive.
+    pub fn extract_all_to_memory(
+        &self,
+        level: &ReadLevel,
+    ) -> Result<std::collections::HashMap<PathBuf, Vec<u8>>, PadError> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .map(|mr| Ok((self.logical_path(mr)?, self.read(mr, level)?)))
+                .collect()
+        })
+    }
+
+    // Duplicate logical paths, and lowercasing or sanitizing, can map several records onto one
+    // output path. The first record in meta table order is kept and the rest are reported with
+    // their logical paths, moved from `targets` to `collisions`, rather than overwriting each
+    // other.
+    fn drop_collisions(&self, output: &mut OutputTargets) {
+        let targets = std::mem::take(&mut output.targets);
+        let mut kept = std::collections::HashMap::new();
+        for (rel_path, mr) in targets {
+            match kept.get(&rel_path) {
+                Some(&first) => {
+                    // Both records already resolved to `rel_path`, so their ids are in range.
+                    let original = |r: &MetaRecord| self.logical_path(r).unwrap_or_default();
+                    log::warn!(
+                        "skipped {}, it collides with {} as {}",
+                        original(mr).display(),
+                        original(first).display(),
+                        rel_path.display()
+                    );
+                    output.collisions.push((rel_path, mr.hash));
+                }
+                None => {
+                    kept.insert(rel_path.clone(), mr);
+                    output.targets.push((rel_path, mr));
+                }
+            }
+        }
+    }
+
+    // Decodes each record up to `level` and hands `f` the logical path along with the bytes
+    // re-encoded by `codec`. Failed records are skipped and returned by hash, as in
+    // `ExtractStats::failures`.
+    pub fn for_each_recompressed(
+        &self,
+        level: &ReadLevel,
+        codec: OutCodec,
+        f: impl Fn(&Path, &[u8]) + Sync,
+    ) -> Vec<(u32, PadError)> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .filter_map(|mr| {
+                    let encoded = self.logical_path(mr).and_then(|logical_path| {
+                        let buf = codec.encode(self.read(mr, level)?)?;
+                        f(&logical_path, &buf);
+                        Ok(())
+                    });
+                    match encoded {
+                        Ok(()) => None,
+                        Err(e) => {
+                            log::warn!("failed {}: {} ({:?})", self.display(mr), e, mr);
+                            Some((mr.hash, e))
+                        }
+                    }
+                })
+                .collect()
+        })
+    }
+
+    // Returns `()` for existing callers; `filter_by_file_stats` returns the `FilterStats` every
+    // other filter does.
+    pub fn filter_by_file(&mut self, pattern: &str) -> Result<(), PadError> {
+        self.filter_by_file_stats(pattern)?;
         Ok(())
     }
 
-    pub fn read(&self, record: &MetaRecord, level: &ReadLevel) -> Result<Vec<u8>, Box<dyn Error>> {
-        // ReadLevel::Raw
-        let mut f = std::fs::File::open(self.package_path(record))?;
-        f.seek(std::io::SeekFrom::Start(record.package_offset as u64))?;
-        let mut buf = vec![0; record.sz_compressed as usize];
-        f.read_exact(&mut buf)?;
+    pub fn filter_by_file_stats(&mut self, pattern: &str) -> Result<FilterStats, PadError> {
+        self.filter_by_file_with(pattern, &FilterOptions::default())
+    }
+
+    pub fn filter_by_file_with(
+        &mut self,
+        pattern: &str,
+        options: &FilterOptions,
+    ) -> Result<FilterStats, PadError> {
+        let re = options.regex(pattern)?;
+        Ok(self.retain_files(|name| re.is_match(name)))
+    }
+
+    // Glob counterpart of `filter_by_file`, matched against the same decoded file names.
+    pub fn filter_by_file_glob(&mut self, pattern: &str) -> Result<FilterStats, PadError> {
+        let glob = globset::Glob::new(pattern)?.compile_matcher();
+        Ok(self.retain_files(|name| glob.is_match(name)))
+    }
+
+    // Keeps the records `f` accepts, given each record with its directory path and file name.
+    // The escape hatch for criteria the other filters don't cover; `f` runs on the rayon pool.
+    // Records with an out of range path or file id are dropped without calling `f`.
+    pub fn retain(&mut self, f: impl Fn(&MetaRecord, &Path, &Path) -> bool + Sync) -> FilterStats {
+        self.retain_records(|meta, mr| match (meta.directory(mr), meta.file_name(mr)) {
+            (Ok(directory), Ok(file_name)) => f(mr, directory, file_name),
+            _ => false,
+        })
+    }
+
+    // Keeps records whose original (decompressed) size falls in `range`, e.g. `1 << 20..`.
+    pub fn filter_by_size(&mut self, range: impl std::ops::RangeBounds<u32> + Sync) -> FilterStats {
+        self.retain_records(|_, mr| range.contains(&mr.sz_original))
+    }
+
+    // Keeps records stored in any of the packages `ids`, e.g. to look into one bad package.
+    pub fn filter_by_package(&mut self, ids: &[u32]) -> FilterStats {
+        let ids: std::collections::HashSet<u32> = ids.iter().copied().collect();
+        self.retain_records(|_, mr| ids.contains(&mr.package_id))
+    }
 
-        let file_name = &self.file_table[record.file_id as usize];
-        let is_dbss = match file_name.to_str() {
-            Some(s) => s.ends_with(".dbss"),
-            None => false,
+    // Keeps files whose final extension equals `ext` (with or without its leading dot) ignoring
+    // ASCII case, so `bss` keeps `de.bss` but not `de.bss.bak` or `de.dbss`.
+    pub fn filter_by_extension(&mut self, ext: &str) -> FilterStats {
+        let ext = ext.strip_prefix('.').unwrap_or(ext);
+        self.retain_files(|name| {
+            matches!(Path::new(name).extension().and_then(|e| e.to_str()), Some(e) if e.eq_ignore_ascii_case(ext))
+        })
+    }
+
+    // Matches on the decoded file name only, not the full path, so no regex escaping is needed.
+    pub fn filter_by_exact_files(
+        &mut self,
+        names: &std::collections::HashSet<String>,
+    ) -> FilterStats {
+        let matches = self.file_matcher(|name| names.contains(name));
+        self.retain_records(|_, mr| matches(mr))
+    }
+
+    // Returns `()` like `filter_by_file`, with `filter_by_path_stats` for the `FilterStats`.
+    pub fn filter_by_path(&mut self, re_pat: &str) -> Result<(), PadError> {
+        self.filter_by_path_stats(re_pat)?;
+        Ok(())
+    }
+
+    pub fn filter_by_path_stats(&mut self, re_pat: &str) -> Result<FilterStats, PadError> {
+        self.filter_by_path_with(re_pat, &FilterOptions::default())
+    }
+
+    pub fn filter_by_path_with(
+        &mut self,
+        re_pat: &str,
+        options: &FilterOptions,
+    ) -> Result<FilterStats, PadError> {
+        let re = options.regex(re_pat)?;
+        Ok(self.retain_paths(|path| re.is_match(path)))
+    }
+
+    // Glob counterpart of `filter_by_path`, matched against the same decoded directory paths
+    // which keep their trailing `/`. Unlike a regex the glob has to match the whole path.
+    pub fn filter_by_path_glob(&mut self, pattern: &str) -> Result<FilterStats, PadError> {
+        let glob = globset::Glob::new(pattern)?.compile_matcher();
+        Ok(self.retain_paths(|path| glob.is_match(path)))
+    }
+
+    // Drops records whose directory path matches, keeping everything else. Exactly the records
+    // `filter_by_path` would keep are dropped.
+    pub fn filter_out_path(&mut self, pattern: &str) -> Result<FilterStats, PadError> {
+        self.filter_out_path_with(pattern, &FilterOptions::default())
+    }
+
+    pub fn filter_out_path_with(
+        &mut self,
+        pattern: &str,
+        options: &FilterOptions,
+    ) -> Result<FilterStats, PadError> {
+        let re = options.regex(pattern)?;
+        let matches = self.path_matcher(|path| re.is_match(path));
+        Ok(self.retain_records(|_, mr| !matches(mr)))
+    }
+
+    // Drops records whose file name matches, keeping everything else. Exactly the records
+    // `filter_by_file` would keep are dropped.
+    pub fn filter_out_file(&mut self, pattern: &str) -> Result<FilterStats, PadError> {
+        self.filter_out_file_with(pattern, &FilterOptions::default())
+    }
+
+    pub fn filter_out_file_with(
+        &mut self,
+        pattern: &str,
+        options: &FilterOptions,
+    ) -> Result<FilterStats, PadError> {
+        let re = options.regex(pattern)?;
+        let matches = self.file_matcher(|name| re.is_match(name));
+        Ok(self.retain_records(|_, mr| !matches(mr)))
+    }
+
+    // A copy of this meta narrowed by `filter(path_pat, file_pat)`, leaving this one untouched.
+    // The package, path and file tables are shared rather than copied, so fanning out several
+    // queries over one loaded archive only costs each its meta table. The copy's `clear_filters`
+    // goes back to this meta's selection at the time of the call. Open package handles and maps
+    // aren't shared; pass a `PackageCache` to `read_with` for that.
+    pub fn clone_filtered(
+        &self,
+        path_pat: Option<&str>,
+        file_pat: Option<&str>,
+    ) -> Result<MetaFile, PadError> {
+        let mut meta = MetaFile {
+            ice: ice_for_key(&self.key)?,
+            key: self.key.clone(),
+            root: self.root.clone(),
+            version: self.version,
+            package_table: self.package_table.clone(),
+            meta_table: self.meta_table.clone(),
+            unfiltered_meta_table: None,
+            hash_index: Default::default(),
+            path_table: self.path_table.clone(),
+            file_table: self.file_table.clone(),
+            undecodable_paths: self.undecodable_paths.clone(),
+            undecodable_files: self.undecodable_files.clone(),
+            max_decompressed_size: self.max_decompressed_size,
+            unencrypted_extensions: self.unencrypted_extensions.clone(),
+            share_packages: self.share_packages,
+            backend: self.backend,
+            open_packages: OpenPackages::default(),
+            mapped_packages: MappedPackages::default(),
+            thread_pool: self.thread_pool.clone(),
+            source: self.source.clone(),
         };
-        if level >= &ReadLevel::Decrypt && !is_dbss {
-            self.ice.decrypt_par(&mut buf);
+        meta.filter(path_pat, file_pat)?;
+        Ok(meta)
+    }
+
+    // Applies an optional path and an optional file pattern in a single parallel pass, keeping
+    // what `filter_by_path` followed by `filter_by_file` would.
+    pub fn filter(
+        &mut self,
+        path_pat: Option<&str>,
+        file_pat: Option<&str>,
+    ) -> Result<FilterStats, PadError> {
+        self.filter_with(path_pat, file_pat, &FilterOptions::default())
+    }
+
+    pub fn filter_with(
+        &mut self,
+        path_pat: Option<&str>,
+        file_pat: Option<&str>,
+        options: &FilterOptions,
+    ) -> Result<FilterStats, PadError> {
+        let path_re = path_pat.map(|p| options.regex(p)).transpose()?;
+        let file_re = file_pat.map(|p| options.regex(p)).transpose()?;
+        let path_matches = path_re.map(|re| self.path_matcher(move |path| re.is_match(path)));
+        let file_matches = file_re.map(|re| self.file_matcher(move |name| re.is_match(name)));
+        Ok(self.retain_records(|_, mr| {
+            path_matches.as_ref().is_none_or(|matches| matches(mr))
+                && file_matches.as_ref().is_none_or(|matches| matches(mr))
+        }))
+    }
+
+    fn retain_files(&mut self, is_match: impl Fn(&str) -> bool + Sync) -> FilterStats {
+        let matches = self.file_matcher(is_match);
+        self.retain_records(|_, mr| matches(mr))
+    }
+
+    // The record test behind `retain_files`, shared with `count_matching_file`.
+    pub(crate) fn file_matcher(
+        &self,
+        is_match: impl Fn(&str) -> bool + Sync,
+    ) -> impl Fn(&MetaRecord) -> bool + Sync {
+        let file_table = self.file_table.clone();
+        move |mr| {
+            file_table
+                .get(mr.file_id as usize)
+                .and_then(|file| file.to_str())
+                .is_some_and(&is_match)
+        }
+    }
+
+    fn retain_records(
+        &mut self,
+        is_match: impl Fn(&MetaFile, &MetaRecord) -> bool + Sync,
+    ) -> FilterStats {
+        self.before_filter();
+        let before = self.meta_table.len();
+        self.meta_table = self.install(|| {
+            self.meta_table
+                .par_iter()
+                .filter(|x| is_match(self, x))
+                .cloned()
+                .collect()
+        });
+        log::debug!(
+            "filter kept {} of {} records",
+            self.meta_table.len(),
+            before
+        );
+        FilterStats::new(before, self.meta_table.len())
+    }
+
+    // Each path is matched once and records are kept by their `path_id`, so the result doesn't
+    // depend on the meta table's order or on earlier filters.
+    fn retain_paths(&mut self, is_match: impl Fn(&str) -> bool + Sync) -> FilterStats {
+        let matches = self.path_matcher(is_match);
+        self.retain_records(|_, mr| matches(mr))
+    }
+
+    // The record test behind `retain_paths`, shared with `count_matching_path`.
+    pub(crate) fn path_matcher(
+        &self,
+        is_match: impl Fn(&str) -> bool + Sync,
+    ) -> impl Fn(&MetaRecord) -> bool + Sync {
+        let matched: Vec<bool> = self.install(|| {
+            self.path_table
+                .par_iter()
+                .map(|pr| is_match(pr.path.to_str().unwrap()))
+                .collect()
+        });
+        move |mr| matched.get(mr.path_id as usize) == Some(&true)
+    }
+
+    // Records of the current selection `filter_by_path(pattern)` would keep, without filtering,
+    // e.g. to preview a pattern as it's typed. `filter_out_path(pattern)` drops the same count.
+    pub fn count_matching_path(&self, pattern: &str) -> Result<usize, PadError> {
+        self.count_matching_path_with(pattern, &FilterOptions::default())
+    }
+
+    pub fn count_matching_path_with(
+        &self,
+        pattern: &str,
+        options: &FilterOptions,
+    ) -> Result<usize, PadError> {
+        let re = options.regex(pattern)?;
+        Ok(self.count_matching(self.path_matcher(|path| re.is_match(path))))
+    }
+
+    // Records of the current selection `filter_by_file(pattern)` would keep, without filtering.
+    // `filter_out_file(pattern)` drops the same count.
+    pub fn count_matching_file(&self, pattern: &str) -> Result<usize, PadError> {
+        self.count_matching_file_with(pattern, &FilterOptions::default())
+    }
+
+    pub fn count_matching_file_with(
+        &self,
+        pattern: &str,
+        options: &FilterOptions,
+    ) -> Result<usize, PadError> {
+        let re = options.regex(pattern)?;
+        Ok(self.count_matching(self.file_matcher(|name| re.is_match(name))))
+    }
+
+    fn count_matching(&self, matches: impl Fn(&MetaRecord) -> bool + Sync) -> usize {
+        self.install(|| self.meta_table.par_iter().filter(|mr| matches(mr)).count())
+    }
+
+    // Restores the meta table to what was parsed, undoing every filter applied since.
+    pub fn clear_filters(&mut self) {
+        if let Some(meta_table) = self.unfiltered_meta_table.take() {
+            self.meta_table = meta_table;
+            self.hash_index = Default::default();
+        }
+    }
+
+    // Reorders the current meta table. Filters keep the order they're given.
+    pub fn sort_by(&mut self, key: MetaSortKey) {
+        let mut meta_table = std::mem::take(&mut self.meta_table);
+        self.install(|| match key {
+            MetaSortKey::FileId => meta_table.par_sort_by_key(file_id_order),
+            MetaSortKey::Package => {
+                meta_table.par_sort_by_key(|mr| (mr.package_id, mr.package_offset))
+            }
+            MetaSortKey::SizeDescending => {
+                meta_table.par_sort_by_key(|mr| std::cmp::Reverse(mr.sz_original))
+            }
+        });
+        self.meta_table = meta_table;
+        self.hash_index = Default::default();
+    }
+
+    // Runs `op` on `thread_pool` when one is set so its parallel iterators stay on that pool.
+    pub(crate) fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        match &self.thread_pool {
+            Some(pool) => pool.install(op),
+            None => op(),
         }
+    }
+
+    fn before_filter(&mut self) {
+        if self.unfiltered_meta_table.is_none() {
+            self.unfiltered_meta_table = Some(self.meta_table.clone());
+        }
+        self.hash_index = Default::default();
+    }
+
+    // Looks up a record in the current meta table by its `hash`, building the index on first
+    // use; the filter and sort methods drop it. Should records share a hash the first in table
+    // order is found. Changes made to `meta_table` directly aren't seen by an index built
+    // before them, so such a record may be missed, but a record with another hash is never
+    // returned.
+    pub fn find_by_hash(&self, hash: u32) -> Option<&MetaRecord> {
+        let index = self.hash_index.get_or_init(|| {
+            let mut index = std::collections::HashMap::with_capacity(self.meta_table.len());
+            for (i, mr) in self.meta_table.iter().enumerate() {
+                index.entry(mr.hash).or_insert(i);
+            }
+            index
+        });
+        index
+            .get(&hash)
+            .and_then(|&i| self.meta_table.get(i))
+            .filter(|mr| mr.hash == hash)
+    }
+
+    // Every record in the current meta table named `name`, whatever its directory, in meta
+    // table order.
+    pub fn find_by_name(&self, name: &str) -> Vec<&MetaRecord> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .filter(|mr| matches!(self.file_name(mr), Ok(f) if f.to_str() == Some(name)))
+                .collect()
+        })
+    }
+
+    // Logical paths more than one record in the current selection resolves to, sorted by path,
+    // with the hashes of those records in meta table order. `extract_many` writes only the first
+    // record of each and skips the rest. `find_path_collisions` keyed by hash instead of index.
+    pub fn find_collisions(&self) -> Vec<(PathBuf, Vec<u32>)> {
+        let mut collisions: Vec<_> = self
+            .find_path_collisions()
+            .into_iter()
+            .map(|(path, indices)| {
+                let hashes = indices.iter().map(|&i| self.meta_table[i].hash).collect();
+                (path, hashes)
+            })
+            .collect();
+        collisions.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        collisions
+    }
+
+    // Each meta record along with its relative path in the archive (path + file name). Records
+    // with an out of range path or file id are left out.
+    pub fn entries(&self) -> impl Iterator<Item = (&MetaRecord, PathBuf)> {
+        self.meta_table
+            .iter()
+            .filter_map(move |mr| Some((mr, self.logical_path(mr).ok()?)))
+    }
+
+    // Logical path of every record in the current selection as a forward slash separated string.
+    // Records with an out of range path or file id are left out.
+    pub fn list(&self) -> Vec<String> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .filter_map(|mr| self.logical_path_string(mr).ok())
+                .collect()
+        })
+    }
+
+    pub(crate) fn logical_path_string(&self, mr: &MetaRecord) -> Result<String, PadError> {
+        let logical_path = self.logical_path(mr)?;
+        Ok(logical_path.to_string_lossy().replace('\\', "/"))
+    }
+
+    // The record with its logical path, package and sizes for logs and messages, e.g.
+    // `character/ai.xml (package 1, 22.5 KiB stored, 86.2 KiB original, hash df9c8eab)`.
+    pub fn display<'a>(&'a self, record: &'a MetaRecord) -> RecordDisplay<'a> {
+        RecordDisplay { meta: self, record }
+    }
+
+    // `display` rendered to a string.
+    pub fn describe(&self, record: &MetaRecord) -> String {
+        self.display(record).to_string()
+    }
+
+    // Path and file table entries holding bytes the meta's encoding couldn't decode, which the
+    // decoder replaced with U+FFFD. A few suggest a damaged meta; most of the table suggests the
+    // wrong encoding was chosen. The entries are the ones the decoder flagged while parsing, so a
+    // name that really holds U+FFFD, as UTF-8 can, isn't listed.
+    pub fn decode_warnings(&self) -> Vec<DecodeWarning> {
+        let paths = self.undecodable_paths.iter().filter_map(|&i| {
+            Some(DecodeWarning {
+                table: "path",
+                index: i,
+                name: self.path_table.get(i)?.path.clone(),
+            })
+        });
+        let files = self.undecodable_files.iter().filter_map(|&i| {
+            Some(DecodeWarning {
+                table: "file",
+                index: i,
+                name: self.file_table.get(i)?.clone(),
+            })
+        });
+        paths.chain(files).collect()
+    }
+
+    // Number of meta records in the current selection. This matches `file_table.len()` when
+    // unfiltered and every file is stored once.
+    pub fn total_file_count(&self) -> usize {
+        self.meta_table.len()
+    }
+
+    // Number of unique `file_id`s referenced by the current selection, which is lower than
+    // `total_file_count` when a file is shared across directories.
+    pub fn distinct_file_count(&self) -> usize {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .map(|mr| mr.file_id)
+                .collect::<std::collections::HashSet<_>>()
+                .len()
+        })
+    }
+
+    // Bytes `extract_many` writes at `ReadLevel::Decompress` for the current selection, e.g. to
+    // check free space before extracting.
+    pub fn total_original_size(&self) -> u64 {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .map(|mr| mr.sz_original as u64)
+                .sum()
+        })
+    }
+
+    // Bytes stored in the packages for the current selection, which is also what Raw and
+    // Decrypt extraction writes.
+    pub fn total_compressed_size(&self) -> u64 {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .map(|mr| mr.sz_compressed as u64)
+                .sum()
+        })
+    }
+
+    // File counts and size totals per package over the current selection, ordered by package id.
+    // Packages with no selected records are left out.
+    pub fn package_stats(&self) -> Vec<PackageStats> {
+        self.install(|| {
+            let by_package = self
+                .meta_table
+                .par_iter()
+                .fold(
+                    std::collections::HashMap::<u32, PackageStats>::new,
+                    |mut acc, mr| {
+                        let stats = acc.entry(mr.package_id).or_insert_with(|| PackageStats {
+                            package_id: mr.package_id,
+                            ..Default::default()
+                        });
+                        stats.file_count += 1;
+                        stats.total_compressed += mr.sz_compressed as u64;
+                        stats.total_original += mr.sz_original as u64;
+                        acc
+                    },
+                )
+                .reduce(std::collections::HashMap::new, |mut acc, other| {
+                    for (package_id, other) in other {
+                        let stats = acc.entry(package_id).or_insert_with(|| PackageStats {
+                            package_id,
+                            ..Default::default()
+                        });
+                        stats.file_count += other.file_count;
+                        stats.total_compressed += other.total_compressed;
+                        stats.total_original += other.total_original;
+                    }
+                    acc
+                });
+            let mut package_stats: Vec<PackageStats> = by_package.into_values().collect();
+            package_stats.sort_unstable_by_key(|stats| stats.package_id);
+            package_stats
+        })
+    }
+
+    // Compression totals and extremes over the current selection. Only the size fields are read,
+    // so this needs neither the packages nor any decoding.
+    pub fn compression_report(&self) -> CompressionReport {
+        self.install(|| {
+            let ratio = |mr: &MetaRecord| mr.sz_compressed as f64 / mr.sz_original as f64;
+            let (total_compressed, total_original, stored_count, ratio_sum, ratio_count) = self
+                .meta_table
+                .par_iter()
+                .map(|mr| {
+                    let has_ratio = mr.sz_original > 0;
+                    (
+                        mr.sz_compressed as u64,
+                        mr.sz_original as u64,
+                        (mr.sz_compressed >= mr.sz_original) as usize,
+                        if has_ratio { ratio(mr) } else { 0.0 },
+                        has_ratio as usize,
+                    )
+                })
+                .reduce(
+                    || (0, 0, 0, 0.0, 0),
+                    |a, b| (a.0 + b.0, a.1 + b.1, a.2 + b.2, a.3 + b.3, a.4 + b.4),
+                );
+            // Ties go to the earlier record so the report doesn't depend on how the work was split.
+            let extreme = |pick_later: fn(f64, f64) -> bool| {
+                self.meta_table
+                    .par_iter()
+                    .enumerate()
+                    .filter(|(_, mr)| mr.sz_original > 0)
+                    .reduce_with(|a, b| {
+                        let (ra, rb) = (ratio(a.1), ratio(b.1));
+                        if pick_later(ra, rb) || (ra == rb && b.0 < a.0) {
+                            b
+                        } else {
+                            a
+                        }
+                    })
+                    .map(|(_, mr)| mr.clone())
+            };
+            CompressionReport {
+                record_count: self.meta_table.len(),
+                total_compressed,
+                total_original,
+                overall_ratio: if total_original == 0 {
+                    0.0
+                } else {
+                    total_compressed as f64 / total_original as f64
+                },
+                mean_ratio: if ratio_count == 0 {
+                    0.0
+                } else {
+                    ratio_sum / ratio_count as f64
+                },
+                stored_count,
+                best: extreme(|a, b| b < a),
+                worst: extreme(|a, b| b > a),
+            }
+        })
+    }
+
+    // Packages the current selection reads from whose files aren't under `root`, sorted by id, so
+    // a partial copy of the game files can be reported before extraction starts.
+    pub fn missing_packages(&self) -> Vec<(u32, PathBuf)> {
+        let package_ids: std::collections::BTreeSet<u32> =
+            self.meta_table.iter().map(|mr| mr.package_id).collect();
+        package_ids
+            .into_iter()
+            .map(|package_id| (package_id, self.root.join(package_file_name(package_id))))
+            .filter(|(package_id, paz_path)| match &self.source {
+                Some(source) => source.open(*package_id).is_err(),
+                None => !paz_path.is_file(),
+            })
+            .collect()
+    }
+
+    pub fn read(&self, record: &MetaRecord, level: &ReadLevel) -> Result<Vec<u8>, PadError> {
+        let mut buf = Vec::new();
+        self.read_into(record, level, &mut buf)?;
+        Ok(buf)
+    }
+
+    // Same as `read` but fetches the block through the caller's `cache` instead of `backend`.
+    // Packages are always read from `root`, even when `source` is set.
+    pub fn read_with(
+        &self,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        cache: &PackageCache,
+    ) -> Result<Vec<u8>, PadError> {
+        check_size(record, level, self.max_decompressed_size)?;
+        let mut buf = vec![0; record.sz_compressed as usize];
+        cache.read_block(
+            record.package_id,
+            &self.package_path(record),
+            self.share_packages,
+            record.package_offset as u64,
+            &mut buf,
+        )?;
+        let unencrypted = self.is_unencrypted(record)?;
+        self.install(|| decode_block(&mut buf, record, level, &self.ice, unencrypted))?;
+        Ok(buf)
+    }
+
+    // Same as `read` but reuses the caller's buffer. The compressed bytes are read and decrypted
+    // in place; quicklz can't decompress into an existing allocation so decompressed records
+    // still replace the buffer.
+    pub fn read_into(
+        &self,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), PadError> {
+        check_size(record, level, self.max_decompressed_size)?;
+        self.read_stored(record, record.sz_compressed as usize, buf)?;
+        let unencrypted = self.is_unencrypted(record)?;
+        self.install(|| decode_block(buf, record, level, &self.ice, unencrypted))
+            .map_err(|e| {
+                log::debug!("decoding {} failed: {}", self.display(record), e);
+                e
+            })
+    }
 
-        if level >= &ReadLevel::Decompress {
-            if record.sz_original > record.sz_compressed
-                || (!is_dbss && !buf.is_empty() && buf[0] == 0x6E)
-            {
-                let mut buf_reader = Cursor::<&[u8]>::new(&buf);
-                buf = quicklz::decompress(&mut buf_reader, record.sz_original)?;
+    // Like `read` but hands the bytes to `w`, returning how many were written. `read` holds the
+    // whole block and then the whole decoded file, peaking around `sz_compressed + sz_original`.
+    // Here uncompressed and quicklz stored blocks are written straight out of the block buffer
+    // and zstd frames are decoded to `w` in chunks, peaking around `sz_compressed`. quicklz has
+    // no incremental API so compressed quicklz blocks still peak like `read`. A zstd frame that
+    // decodes to the wrong size is reported after its bytes were written.
+    pub fn read_streaming(
+        &self,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        w: &mut dyn Write,
+    ) -> Result<u64, PadError> {
+        if level < &ReadLevel::Decompress {
+            let buf = self.read(record, level)?;
+            w.write_all(&buf)?;
+            return Ok(buf.len() as u64);
+        }
+        check_size(record, level, self.max_decompressed_size)?;
+        let mut buf = self.read(record, &ReadLevel::Decrypt)?;
+        let payload: &[u8] = match detect_compression(&buf) {
+            Compression::QuickLz if buf[0] & 0x01 == 0 => {
+                let header_len = if buf[0] & 0x02 != 0 { 9 } else { 3 };
+                let (stored_len, _) = sniff::quicklz_header(&buf).unwrap_or_default();
+                buf.get(header_len..stored_len).unwrap_or_default()
             }
-            if record.sz_original < record.sz_compressed {
-                buf = buf[0..record.sz_original as usize].to_vec();
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let len = zstd::zstd_safe::find_frame_compressed_size(&buf).map_err(|code| {
+                    PadError::Decompress(zstd::zstd_safe::get_error_name(code).to_string())
+                })?;
+                let mut decoder = zstd::stream::read::Decoder::with_buffer(&buf[..len])?;
+                let written = std::io::copy(&mut decoder, w)?;
+                if written != record.sz_original as u64 {
+                    return Err(PadError::SizeMismatch {
+                        expected: record.sz_original,
+                        got: written as usize,
+                    });
+                }
+                return Ok(written);
+            }
+            Compression::QuickLz => {
+                decompress_block(&mut buf, record)?;
+                &buf
+            }
+            Compression::None if record.sz_original > record.sz_compressed => {
+                return Err(PadError::Decompress(
+                    "block has no valid quicklz header".to_string(),
+                ));
+            }
+            Compression::None => &buf[..record.sz_original as usize],
+        };
+        if payload.len() != record.sz_original as usize {
+            return Err(PadError::SizeMismatch {
+                expected: record.sz_original,
+                got: payload.len(),
+            });
+        }
+        w.write_all(payload)?;
+        Ok(payload.len() as u64)
+    }
+
+    // The first `max_len` bytes `read` would return. Uncompressed and quicklz stored blocks are
+    // only read and decrypted as far as needed, and zstd frames are decoded until `max_len` bytes
+    // come out. Compressed quicklz blocks are decoded whole and truncated.
+    pub fn read_prefix(
+        &self,
+        record: &MetaRecord,
+        level: &ReadLevel,
+        max_len: usize,
+    ) -> Result<Vec<u8>, PadError> {
+        let block_len = record.sz_compressed as usize;
+        // ICE decrypts each 8 byte block on its own, so a prefix of whole blocks decrypts the same
+        // as the full block does. The extra 9 bytes cover a quicklz header. Clamped to the block
+        // first so that `usize::MAX` reads it all instead of overflowing.
+        let len = (max_len.min(block_len) as u64 + 9)
+            .next_multiple_of(8)
+            .max(16)
+            .min(block_len as u64) as usize;
+        let mut buf = Vec::new();
+        self.read_stored(record, len, &mut buf)?;
+        if level >= &ReadLevel::Decrypt && !self.is_unencrypted(record)? {
+            self.install(|| self.ice.decrypt_par(&mut buf));
+        }
+        if level < &ReadLevel::Decompress {
+            buf.truncate(max_len);
+            return Ok(buf);
+        }
+        let sz_original = record.sz_original as usize;
+        let payload = match detect_block_compression(&buf, block_len) {
+            Compression::QuickLz if buf[0] & 0x01 == 0 => {
+                let header_len = if buf[0] & 0x02 != 0 { 9 } else { 3 };
+                let (stored_len, _) = sniff::quicklz_header(&buf).unwrap_or_default();
+                (stored_len == header_len + sz_original).then_some(header_len)
             }
+            Compression::None if record.sz_original <= record.sz_compressed => Some(0),
+            _ => None,
+        };
+        if let Some(start) = payload {
+            return Ok(buf[start..(start + max_len.min(sz_original)).min(buf.len())].to_vec());
+        }
+        #[cfg(feature = "zstd")]
+        if buf.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+            check_size(record, level, self.max_decompressed_size)?;
+            let buf = self.read(record, &ReadLevel::Decrypt)?;
+            let len = zstd::zstd_safe::find_frame_compressed_size(&buf).map_err(|code| {
+                PadError::Decompress(zstd::zstd_safe::get_error_name(code).to_string())
+            })?;
+            let decoder = zstd::stream::read::Decoder::with_buffer(&buf[..len])?;
+            let mut prefix = Vec::new();
+            decoder.take(max_len as u64).read_to_end(&mut prefix)?;
+            return Ok(prefix);
         }
+        let mut buf = self.read(record, level)?;
+        buf.truncate(max_len);
         Ok(buf)
     }
 
+    // The type the start of the decoded record looks like, by the `sniff::SIGNATURES` magic
+    // numbers, e.g. to tag or rename files archived without an extension. Only the prefix the
+    // signatures need is decoded where `read_prefix` can stop early. Unreadable records and
+    // unknown content are both `None`.
+    pub fn sniff_type(&self, record: &MetaRecord) -> Option<&'static str> {
+        self.sniff_type_with(record, &[])
+    }
+
+    // Like `sniff_type` with `extra` signatures checked ahead of the built-in ones, e.g. for the
+    // game's own formats.
+    pub fn sniff_type_with(
+        &self,
+        record: &MetaRecord,
+        extra: &[sniff::Signature],
+    ) -> Option<&'static str> {
+        let prefix = self
+            .read_prefix(record, &ReadLevel::Decompress, sniff::prefix_len(extra))
+            .ok()?;
+        sniff::sniff_extension(&prefix, extra)
+    }
+
+    // Decrypts just the start of the record's block to report its compression header, without
+    // reading or decompressing the rest.
+    pub fn block_info(&self, record: &MetaRecord) -> Result<BlockInfo, PadError> {
+        let mut buf = Vec::new();
+        self.read_stored(record, (record.sz_compressed as usize).min(16), &mut buf)?;
+        if !self.is_unencrypted(record)? {
+            self.install(|| self.ice.decrypt_par(&mut buf));
+        }
+        let compression = detect_block_compression(&buf, record.sz_compressed as usize);
+        let (sz_compressed, sz_original) = match compression {
+            Compression::QuickLz => {
+                let (compressed, original) = sniff::quicklz_header(&buf).unwrap_or_default();
+                (compressed as u32, original as u32)
+            }
+            _ => (record.sz_compressed, record.sz_original),
+        };
+        Ok(BlockInfo {
+            compressed: compression != Compression::None,
+            sz_compressed,
+            sz_original,
+            first_byte: buf.first().copied(),
+        })
+    }
+
+    // Reads the first `len` bytes of the record's block as stored in its package.
+    fn read_stored(
+        &self,
+        record: &MetaRecord,
+        len: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), PadError> {
+        if self.source.is_some() {
+            return self.read_reader_block(record, len, buf);
+        }
+        match self.backend {
+            ReadBackend::File => read_block(
+                &self.package_path(record),
+                record,
+                self.share_packages,
+                len,
+                buf,
+            )?,
+            ReadBackend::CachedFile => {
+                buf.clear();
+                buf.resize(len, 0);
+                self.open_packages.read_block(
+                    &self.package_path(record),
+                    record,
+                    self.share_packages,
+                    buf,
+                )?
+            }
+            ReadBackend::Mmap => {
+                let map = self.mapped_packages.get(
+                    record.package_id,
+                    &self.package_path(record),
+                    self.share_packages,
+                )?;
+                let end = check_block_end(
+                    record.package_id,
+                    record.package_offset as u64,
+                    record.sz_compressed as u64,
+                    map.len() as u64,
+                )?;
+                let start = record.package_offset as usize;
+                buf.clear();
+                buf.extend_from_slice(&map[start..(start + len).min(end as usize)]);
+            }
+        }
+        Ok(())
+    }
+
+    // Blocks of files with one of `unencrypted_extensions` are stored unencrypted.
+    fn is_unencrypted(&self, record: &MetaRecord) -> Result<bool, PadError> {
+        let file_name =
+            self.file_table
+                .get(record.file_id as usize)
+                .ok_or(PadError::IndexOutOfRange {
+                    table: "file",
+                    index: record.file_id as usize,
+                })?;
+        Ok(is_unencrypted_name(file_name, &self.unencrypted_extensions))
+    }
+
+    // The compression `read` detects on the record's decrypted block, for debugging odd records.
+    // Without the zstd feature zstd frames aren't recognized and report as `None`.
+    pub fn compression_of(&self, record: &MetaRecord) -> Result<Compression, PadError> {
+        let buf = self.read(record, &ReadLevel::Decrypt)?;
+        Ok(detect_compression(&buf))
+    }
+
+    // The key package blocks are decrypted with.
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    // Swaps the key used for package blocks, e.g. after loading a meta with another region's
+    // key, without re-parsing. Everything that decrypts blocks picks it up: `read` and its
+    // variants at `Decrypt` and above, extraction, `block_info` and `compression_of`. The path
+    // and file tables were decrypted while parsing and are kept as is, so if the parse key was
+    // wrong the names are too and the meta has to be parsed again with the right one.
+    // Fails with `PadError::BadKeyLength`, keeping the current key, for a key `ice_for_key`
+    // doesn't accept.
+    pub fn rekey(&mut self, key: &[u8]) -> Result<(), PadError> {
+        self.ice = ice_for_key(key)?;
+        self.key = key.to_vec();
+        Ok(())
+    }
+
+    // Number of bytes `read` returns (and `extract` writes) for the record at `level`.
+    // Decryption doesn't change the block length so Decrypt reports the compressed size too.
+    pub fn output_size(&self, record: &MetaRecord, level: &ReadLevel) -> u64 {
+        match level {
+            ReadLevel::Raw | ReadLevel::Decrypt => record.sz_compressed as u64,
+            ReadLevel::Decompress => record.sz_original as u64,
+        }
+    }
+
     pub fn package_name(&self, record: &MetaRecord) -> PathBuf {
-        PathBuf::from(format!("PAD{:05}.paz", record.package_id))
+        package_file_name(record.package_id)
     }
 
     pub fn package_path(&self, record: &MetaRecord) -> PathBuf {
@@ -201,7 +2137,296 @@ impl MetaFile {
     }
 }
 
-#[derive(Debug)]
+// Sizes in B up to a KiB, then in KiB, MiB or GiB to one decimal place.
+fn human_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+// Archive paths come from decrypted meta strings, so a corrupt or malicious meta could hold `..`
+// components, a root or a drive prefix that would escape the output directory once joined.
+pub(crate) fn checked_relative(rel_path: &Path) -> Result<&Path, PadError> {
+    let escapes = rel_path.components().any(|c| {
+        !matches!(
+            c,
+            std::path::Component::Normal(_) | std::path::Component::CurDir
+        )
+    });
+    if escapes {
+        return Err(PadError::UnsafePath(rel_path.to_path_buf()));
+    }
+    Ok(rel_path)
+}
+
+fn create_output(out_file: &Path) -> Result<std::io::BufWriter<std::fs::File>, PadError> {
+    if let Some(parent) = out_file.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("create dir {} failed: {}", parent.display(), e),
+            )
+        })?;
+    }
+    Ok(std::io::BufWriter::new(std::fs::File::create(out_file)?))
+}
+
+// ICE takes 8 bytes of key per level, except that the thin-ICE level 0 the game itself uses and
+// level 1 both take 8. An 8 byte key picks level 0 and an 8n byte key (n > 1) picks level n.
+pub fn ice_for_key(key: &[u8]) -> Result<Ice, PadError> {
+    let level = match key.len() {
+        8 => 0,
+        len if len > 8 && len % 8 == 0 => len / 8,
+        len => return Err(PadError::BadKeyLength(len)),
+    };
+    Ok(Ice::new(level, key))
+}
+
+// Hashes listed in the journal and the length of its complete lines.
+fn read_journal(journal: &Path) -> Result<(std::collections::HashSet<u32>, u64), PadError> {
+    let contents = match std::fs::read_to_string(journal) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let complete = &contents[..contents.rfind('\n').map_or(0, |end| end + 1)];
+    let hashes = complete
+        .lines()
+        .filter(|line| line.len() == 8)
+        .filter_map(|line| u32::from_str_radix(line, 16).ok())
+        .collect();
+    Ok((hashes, complete.len() as u64))
+}
+
+fn flat_name(rel_path: &Path, record: &MetaRecord, separator: &str) -> String {
+    let file_name = rel_path.file_name().unwrap_or_default().to_string_lossy();
+    format!("{}{}{}", record.package_id, separator, file_name)
+}
+
+fn flatten<'a>(
+    targets: Vec<(PathBuf, &'a MetaRecord)>,
+    separator: &str,
+) -> Vec<(PathBuf, &'a MetaRecord)> {
+    let names: Vec<String> = targets
+        .iter()
+        .map(|(rel_path, mr)| flat_name(rel_path, mr, separator))
+        .collect();
+    let mut counts = std::collections::HashMap::<&str, usize>::new();
+    for name in &names {
+        *counts.entry(name).or_default() += 1;
+    }
+    names
+        .iter()
+        .zip(targets)
+        .map(|(name, (_, mr))| {
+            if counts[name.as_str()] == 1 {
+                return (PathBuf::from(name), mr);
+            }
+            let name = Path::new(name);
+            let stem = name.file_stem().unwrap_or_default().to_string_lossy();
+            let mut unique = format!("{}{}{:08x}", stem, separator, mr.hash);
+            if let Some(extension) = name.extension() {
+                unique.push('.');
+                unique.push_str(&extension.to_string_lossy());
+            }
+            (PathBuf::from(unique), mr)
+        })
+        .collect()
+}
+
+fn package_file_name(package_id: u32) -> PathBuf {
+    PathBuf::from(format!("PAD{:05}.paz", package_id))
+}
+
+// Reads a record straight out of the given package file, ignoring the `root` and package naming
+// conventions of a loaded `MetaFile`. Without the file table the file's extension can't be checked
+// so the block is always treated as encrypted.
+pub fn read_from_package_file(
+    paz_path: &Path,
+    key: &[u8],
+    record: &MetaRecord,
+    level: &ReadLevel,
+) -> Result<Vec<u8>, PadError> {
+    let ice = ice_for_key(key)?;
+    check_size(record, level, DEFAULT_MAX_DECOMPRESSED_SIZE)?;
+    let mut buf = Vec::new();
+    read_block(
+        paz_path,
+        record,
+        true,
+        record.sz_compressed as usize,
+        &mut buf,
+    )?;
+    decode_block(&mut buf, record, level, &ice, false)?;
+    Ok(buf)
+}
+
+fn check_size(
+    record: &MetaRecord,
+    level: &ReadLevel,
+    max_decompressed_size: u64,
+) -> Result<(), PadError> {
+    if level >= &ReadLevel::Decompress && record.sz_original as u64 > max_decompressed_size {
+        return Err(PadError::SuspiciousSize {
+            sz_original: record.sz_original,
+            max_decompressed_size,
+        });
+    }
+    Ok(())
+}
+
+// Packages are only ever opened for reading. On Windows the share mode is set explicitly so
+// extraction can run while the game holds the packages open; with sharing disabled the open
+// fails instead if anything else has the package open. Unix has no share modes so the flag
+// has no effect there.
+pub(crate) fn open_package(
+    paz_path: &Path,
+    share_packages: bool,
+) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.read(true);
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::OpenOptionsExt;
+        const FILE_SHARE_READ: u32 = 0x1;
+        const FILE_SHARE_WRITE: u32 = 0x2;
+        const FILE_SHARE_DELETE: u32 = 0x4;
+        options.share_mode(if share_packages {
+            FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE
+        } else {
+            0
+        });
+    }
+    #[cfg(not(windows))]
+    let _ = share_packages;
+    options.open(paz_path)
+}
+
+// Reads the first `len` bytes of the record's block, checking the whole block fits the package.
+fn read_block(
+    paz_path: &Path,
+    record: &MetaRecord,
+    share_packages: bool,
+    len: usize,
+    buf: &mut Vec<u8>,
+) -> Result<(), PadError> {
+    let mut f = open_package(paz_path, share_packages)?;
+    check_block_end(
+        record.package_id,
+        record.package_offset as u64,
+        record.sz_compressed as u64,
+        f.metadata()?.len(),
+    )?;
+    f.seek(std::io::SeekFrom::Start(record.package_offset as u64))?;
+    buf.clear();
+    buf.resize(len, 0);
+    f.read_exact(buf)?;
+    Ok(())
+}
+
+// Offsets and sizes are u32 on disk so a block can't start past 4 GiB into its package, though
+// it may end past it. The arithmetic is done in u64 so such a block is still addressable, and
+// a block running past the end of the actual package file is reported rather than read short.
+pub(crate) fn check_block_end(
+    package_id: u32,
+    offset: u64,
+    len: u64,
+    package_size: u64,
+) -> Result<u64, PadError> {
+    let end = offset + len;
+    if end > package_size {
+        return Err(PadError::OffsetOverflow {
+            package_id,
+            end,
+            package_size,
+        });
+    }
+    Ok(end)
+}
+
+// Stored as well as compressed blocks are quicklz wrapped (a stored block's flag byte is the
+// 0x6E this used to key on). The header's compressed size has to account for the whole block
+// short of the cipher block padding. A decompressed size disagreeing with sz_original is left
+// for the length check after decoding to report.
+fn detect_compression(buf: &[u8]) -> Compression {
+    detect_block_compression(buf, buf.len())
+}
+
+// Detection from the start of a block `block_len` bytes long, padding included.
+fn detect_block_compression(prefix: &[u8], block_len: usize) -> Compression {
+    #[cfg(feature = "zstd")]
+    if prefix.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        return Compression::Zstd;
+    }
+    if sniff::is_quicklz_block(prefix, block_len) {
+        return Compression::QuickLz;
+    }
+    Compression::None
+}
+
+fn decode_block(
+    buf: &mut Vec<u8>,
+    record: &MetaRecord,
+    level: &ReadLevel,
+    ice: &Ice,
+    unencrypted: bool,
+) -> Result<(), PadError> {
+    if level >= &ReadLevel::Decrypt && !unencrypted {
+        ice.decrypt_par(buf);
+    }
+
+    if level >= &ReadLevel::Decompress {
+        decompress_block(buf, record)?;
+    }
+    Ok(())
+}
+
+// Decompresses a decrypted block in place, checking the result against the record's size.
+fn decompress_block(buf: &mut Vec<u8>, record: &MetaRecord) -> Result<(), PadError> {
+    match detect_compression(buf) {
+        Compression::QuickLz => {
+            let mut buf_reader = Cursor::<&[u8]>::new(buf);
+            *buf = quicklz::decompress(&mut buf_reader, record.sz_original)
+                .map_err(|e| PadError::Decompress(e.to_string()))?;
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            // The frame is followed by cipher block padding which zstd won't skip itself.
+            let len = zstd::zstd_safe::find_frame_compressed_size(buf).map_err(|code| {
+                PadError::Decompress(zstd::zstd_safe::get_error_name(code).to_string())
+            })?;
+            *buf = zstd::bulk::decompress(&buf[..len], record.sz_original as usize)
+                .map_err(|e| PadError::Decompress(e.to_string()))?;
+        }
+        Compression::None if record.sz_original > record.sz_compressed => {
+            return Err(PadError::Decompress(
+                "block has no valid quicklz header".to_string(),
+            ));
+        }
+        Compression::None => (),
+    }
+    if record.sz_original < record.sz_compressed {
+        buf.truncate(record.sz_original as usize);
+    }
+    if buf.len() != record.sz_original as usize {
+        return Err(PadError::SizeMismatch {
+            expected: record.sz_original,
+            got: buf.len(),
+        });
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PackageRecord {
     pub id: u32,
     pub hash: u32,
@@ -227,6 +2452,7 @@ impl PackageRecord {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MetaRecord {
     pub hash: u32,
     pub path_id: u32,
@@ -237,6 +2463,12 @@ pub struct MetaRecord {
     pub sz_original: u32,
 }
 
+// Records sharing a file id, one file stored under several directories, are ordered by their
+// block so the parsed order doesn't depend on the meta file's record order.
+fn file_id_order(mr: &MetaRecord) -> (u32, u32, u32) {
+    (mr.file_id, mr.package_id, mr.package_offset)
+}
+
 impl MetaRecord {
     fn from_le_bytes(bytes: &[u8; 28]) -> MetaRecord {
         let mut reader = Cursor::new(bytes);
@@ -259,7 +2491,8 @@ impl MetaRecord {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct PathRecord {
     pub path: PathBuf,
     pub file_range: std::ops::Range<usize>,
@@ -273,39 +2506,90 @@ impl PathRecord {
         }
     }
 
-    fn many_from_encrypted_le_bytes(bytes: &mut [u8], ice: &Ice) -> Vec<PathRecord> {
+    // Also returns the indices of the paths the decoder reported errors for.
+    fn many_from_encrypted_le_bytes(
+        bytes: &mut [u8],
+        ice: &Ice,
+        encoding: &'static Encoding,
+    ) -> (Vec<PathRecord>, Vec<usize>) {
         ice.decrypt_par(bytes);
-        let trimmed_len = bytes.len() - bytes.iter().rev().position(|x| *x != 0).unwrap() + 1;
-        let bytes = &mut bytes[..trimmed_len];
+        let bytes = &bytes[..unpadded_len(bytes)];
 
-        let mut path_table = Vec::new();
-        let mut reader = Cursor::new(bytes);
-        while (reader.position() as usize) < trimmed_len {
-            let start = reader.read_u32::<LittleEndian>().unwrap();
-            let end = start + reader.read_u32::<LittleEndian>().unwrap();
-            let mut buf = Vec::new();
-            reader.read_until(0, &mut buf).unwrap();
-            buf.pop();
-            let record = PathRecord::from_raw_parts(
-                &encoding_rs::EUC_KR.decode_without_bom_handling(&buf).0,
-                start as usize,
-                end as usize,
-            );
-            path_table.push(record);
+        // Each entry is a u32 start and count followed by a terminated name. The boundaries are
+        // walked in order since the headers can hold zero bytes a parallel scan would mistake
+        // for terminators; the walk only looks for terminators and the decoding is parallel.
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while pos < bytes.len() {
+            let name_start = (pos + 8).min(bytes.len());
+            let name_end = match bytes[name_start..].iter().position(|x| *x == 0) {
+                Some(i) => name_start + i,
+                // The last path's terminator was trimmed along with the padding.
+                None => bytes.len(),
+            };
+            entries.push((pos, name_start..name_end));
+            pos = name_end + 1;
         }
-        path_table
+        let (records, had_errors): (Vec<_>, Vec<_>) = entries
+            .into_par_iter()
+            .map(|(header, name)| {
+                let start = padded_u32(bytes, header);
+                let end = start + padded_u32(bytes, header + 4);
+                let (path, had_errors) = encoding.decode_without_bom_handling(&bytes[name]);
+                let record = PathRecord::from_raw_parts(&path, start as usize, end as usize);
+                (record, had_errors)
+            })
+            .unzip();
+        (records, flagged(&had_errors))
     }
 }
 
+// Reads a little endian u32 at `at`, treating bytes past the end as zero. A final entry with a
+// zero count and an empty name loses the tail of its header to the padding trim.
+fn padded_u32(bytes: &[u8], at: usize) -> u32 {
+    let mut word = [0u8; 4];
+    if let Some(available) = bytes.get(at..) {
+        let n = available.len().min(4);
+        word[..n].copy_from_slice(&available[..n]);
+    }
+    u32::from_le_bytes(word)
+}
+
+// Length of a decrypted string block without the zero padding that rounds it up to the cipher
+// block size. The final string's terminator goes with the padding.
+fn unpadded_len(bytes: &[u8]) -> usize {
+    bytes.iter().rposition(|x| *x != 0).map_or(0, |i| i + 1)
+}
+
 struct FileRecord; // PathBuf
 impl FileRecord {
-    fn many_from_encrypted_le_bytes(bytes: &mut [u8], ice: &Ice) -> Vec<PathBuf> {
+    // Also returns the indices of the names the decoder reported errors for.
+    fn many_from_encrypted_le_bytes(
+        bytes: &mut [u8],
+        ice: &Ice,
+        encoding: &'static Encoding,
+    ) -> (Vec<PathBuf>, Vec<usize>) {
         ice.decrypt_par(bytes);
-        let trimmed_len = bytes.len() - bytes.iter().rev().position(|x| x != &0u8).unwrap();
-        bytes[..trimmed_len]
+        let trimmed_len = unpadded_len(bytes);
+        if trimmed_len == 0 {
+            return (Vec::new(), Vec::new());
+        }
+        let (files, had_errors): (Vec<_>, Vec<_>) = bytes[..trimmed_len]
             .par_split(|x| x == &0u8)
-            .map(|x| encoding_rs::EUC_KR.decode_without_bom_handling(x).0)
-            .map(|x| PathBuf::from(x.to_string()))
-            .collect()
+            .map(|x| {
+                let (name, had_errors) = encoding.decode_without_bom_handling(x);
+                (PathBuf::from(name.to_string()), had_errors)
+            })
+            .unzip();
+        (files, flagged(&had_errors))
     }
 }
+
+// Indices of the set flags.
+fn flagged(flags: &[bool]) -> Vec<usize> {
+    flags
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &flag)| flag.then_some(i))
+        .collect()
+}
@@ -0,0 +1,77 @@
+// Manifests describe the loaded tables without extracting anything, for diffing archive
+// contents across patches.
+use crate::{MetaFile, PadError};
+#[cfg(feature = "serde")]
+use crate::{MetaRecord, PackageRecord};
+use std::io::Write;
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonManifest<'a> {
+    version: u32,
+    packages: &'a [PackageRecord],
+    entries: Vec<JsonEntry<'a>>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize)]
+struct JsonEntry<'a> {
+    path: String,
+    #[serde(flatten)]
+    record: &'a MetaRecord,
+}
+
+impl MetaFile {
+    // One row per meta record in the current selection, preceded by a header row.
+    pub fn write_manifest_csv(&self, w: &mut dyn Write) -> Result<(), PadError> {
+        writeln!(
+            w,
+            "path,file,package_id,package_offset,sz_compressed,sz_original,hash"
+        )?;
+        for mr in &self.meta_table {
+            let logical_path = self.logical_path_string(mr)?;
+            let file = self.file_name(mr)?.to_string_lossy();
+            writeln!(
+                w,
+                "{},{},{},{},{},{},{}",
+                csv_field(&logical_path),
+                csv_field(&file),
+                mr.package_id,
+                mr.package_offset,
+                mr.sz_compressed,
+                mr.sz_original,
+                mr.hash
+            )?;
+        }
+        Ok(())
+    }
+
+    // The version, the package table and one entry per meta record in the current selection
+    // holding its logical path alongside the record fields.
+    #[cfg(feature = "serde")]
+    pub fn to_manifest_json(&self) -> Result<String, PadError> {
+        let manifest = JsonManifest {
+            version: self.version,
+            packages: &self.package_table,
+            entries: self
+                .meta_table
+                .iter()
+                .map(|mr| {
+                    Ok(JsonEntry {
+                        path: self.logical_path_string(mr)?,
+                        record: mr,
+                    })
+                })
+                .collect::<Result<_, PadError>>()?,
+        };
+        Ok(serde_json::to_string(&manifest)?)
+    }
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
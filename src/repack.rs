@@ -0,0 +1,54 @@
+// The repack index records where every extracted file came from so that a repacker can
+// rebuild the meta tables from the extracted files alone. The `write_manifest` sidecar is the
+// same index over the output paths.
+use crate::{MetaRecord, PadError};
+use std::io::prelude::*;
+use std::path::Path;
+use std::path::PathBuf;
+
+// Paths are written with `/` separators whatever the platform.
+pub(crate) fn write_index(
+    index_path: &Path,
+    entries: &[(PathBuf, &MetaRecord)],
+) -> Result<(), PadError> {
+    let mut w = std::io::BufWriter::new(std::fs::File::create(index_path)?);
+    writeln!(w, "[")?;
+    for (i, (path, mr)) in entries.iter().enumerate() {
+        let sep = if i + 1 < entries.len() { "," } else { "" };
+        writeln!(
+            w,
+            "  {{\"path\": {}, \"hash\": {}, \"path_id\": {}, \"file_id\": {}, \"package_id\": {}, \
+             \"package_offset\": {}, \"sz_compressed\": {}, \"sz_original\": {}}}{}",
+            json_string(&path.to_string_lossy().replace('\\', "/")),
+            mr.hash,
+            mr.path_id,
+            mr.file_id,
+            mr.package_id,
+            mr.package_offset,
+            mr.sz_compressed,
+            mr.sz_original,
+            sep
+        )?;
+    }
+    writeln!(w, "]")?;
+    w.flush()?;
+    Ok(())
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,65 @@
+// Output path rewriting for filesystems that reject some of the archive's names.
+use std::path::{Component, Path, PathBuf};
+
+// Device names Windows reserves in every directory, whatever the extension.
+const RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// When `MetaFile::extract_many_with_options` rewrites output names Windows can't create.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizeNames {
+    /// Writes names exactly as archived.
+    Never,
+    /// Sanitizes only when built for Windows, leaving names intact elsewhere.
+    #[default]
+    OnWindows,
+    /// Sanitizes on every platform, e.g. for output later copied to a Windows share.
+    Always,
+}
+
+impl SanitizeNames {
+    pub(crate) fn applies(self) -> bool {
+        match self {
+            SanitizeNames::Never => false,
+            SanitizeNames::OnWindows => cfg!(windows),
+            SanitizeNames::Always => true,
+        }
+    }
+}
+
+// Rewrites each component of `rel_path` Windows would refuse: the characters `<>:"\|?*` and
+// control characters become `_`, as do trailing dots and spaces, and a reserved device name such
+// as `con` or `lpt1.txt` gets `_` after its stem (`con_`, `lpt1_.txt`). Other components are
+// returned unchanged. Distinct names can sanitize to the same path.
+pub fn sanitize_windows_path(rel_path: &Path) -> PathBuf {
+    rel_path
+        .components()
+        .map(|c| match c {
+            Component::Normal(name) => PathBuf::from(sanitize_name(&name.to_string_lossy())),
+            other => PathBuf::from(other.as_os_str()),
+        })
+        .collect()
+}
+
+fn sanitize_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let kept = sanitized.trim_end_matches(['.', ' ']).len();
+    let trailing = sanitized.len() - kept;
+    sanitized.truncate(kept);
+    sanitized.extend(std::iter::repeat_n('_', trailing));
+    let stem_len = sanitized.find('.').unwrap_or(sanitized.len());
+    let stem = &sanitized[..stem_len];
+    if RESERVED_NAMES.iter().any(|r| stem.eq_ignore_ascii_case(r)) {
+        sanitized.insert(stem_len, '_');
+    }
+    sanitized
+}
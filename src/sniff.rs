@@ -0,0 +1,93 @@
+// Content sniffing by magic numbers, used to correct misleading or missing file extensions.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Signature {
+    pub extension: &'static str,
+    pub offset: usize,
+    pub magic: &'static [u8],
+}
+
+// The game's own `paac` and `bss` formats aren't listed: their headers aren't documented and
+// no package data is checked in to confirm a magic against, so a guessed one would misname real
+// files. Callers that know a layout can pass it to `MetaFile::sniff_type_with` or in
+// `ExtractOptions::extra_signatures`.
+pub const SIGNATURES: &[Signature] = &[
+    Signature {
+        extension: "png",
+        offset: 0,
+        magic: b"\x89PNG\r\n\x1A\n",
+    },
+    Signature {
+        extension: "ogg",
+        offset: 0,
+        magic: b"OggS",
+    },
+    Signature {
+        extension: "dds",
+        offset: 0,
+        magic: b"DDS ",
+    },
+    Signature {
+        extension: "xml",
+        offset: 0,
+        magic: b"<?xml",
+    },
+    Signature {
+        extension: "xml",
+        offset: 0,
+        magic: b"\xEF\xBB\xBF<?xml",
+    },
+];
+
+// Caller supplied signatures take precedence over the built-in table. Blocks that are still
+// quicklz wrapped (anything below ReadLevel::Decompress) are reported as `qlz`.
+pub fn sniff_extension(buf: &[u8], extra: &[Signature]) -> Option<&'static str> {
+    extra
+        .iter()
+        .chain(SIGNATURES.iter())
+        .find(|sig| {
+            buf.len() >= sig.offset + sig.magic.len()
+                && &buf[sig.offset..sig.offset + sig.magic.len()] == sig.magic
+        })
+        .map(|sig| sig.extension)
+        .or_else(|| is_quicklz_block(buf, buf.len()).then_some("qlz"))
+}
+
+// Bytes of content needed to check every signature in `extra` and the built-in table.
+pub(crate) fn prefix_len(extra: &[Signature]) -> usize {
+    extra
+        .iter()
+        .chain(SIGNATURES.iter())
+        .map(|sig| sig.offset + sig.magic.len())
+        .max()
+        .unwrap_or_default()
+}
+
+// The quicklz header flag byte always has bit 6 set and encodes the compression level (1 or 3)
+// in bits 2-3. Bit 1 selects a 9 byte header over a 3 byte one; either way the header's
+// compressed size covers the whole block short of the cipher block padding, which rules out
+// most accidental matches. `prefix` is the start of a block `block_len` bytes long. Decoding
+// detects blocks by the same rule, so a block sniffed as `qlz` is one decompression unwraps.
+pub(crate) fn is_quicklz_block(prefix: &[u8], block_len: usize) -> bool {
+    matches!(quicklz_header(prefix), Some((compressed, _))
+        if compressed <= block_len && block_len - compressed < 8)
+}
+
+// The compressed and decompressed sizes from a quicklz header, if `buf` starts with a plausible
+// one. Bit 0 of the flag byte, set for compressed and clear for stored blocks, isn't checked.
+pub(crate) fn quicklz_header(buf: &[u8]) -> Option<(usize, usize)> {
+    let flag = *buf.first()?;
+    let level = (flag >> 2) & 0x03;
+    if flag & 0x40 == 0 || (level != 1 && level != 3) {
+        return None;
+    }
+    if flag & 0x02 != 0 {
+        let header = buf.get(1..9)?;
+        let compressed = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        let decompressed = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        Some((compressed as usize, decompressed as usize))
+    } else {
+        let header = buf.get(1..3)?;
+        Some((header[0] as usize, header[1] as usize))
+    }
+}
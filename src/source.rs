@@ -0,0 +1,121 @@
+// Where the meta and the `.paz` packages are read from. `MetaFile::new` and friends read them from
+// a directory; a `PackageSource` lets them come from anywhere seekable instead, like an object
+// store, another container format or an application's virtual filesystem.
+use crate::{
+    check_block_end, hash, open_package, package_file_name, MetaFile, MetaRecord, PadError,
+};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+pub trait PackageSource: std::fmt::Debug + Send + Sync {
+    // The package with the given id, failing with `ErrorKind::NotFound` if there is none.
+    fn open(&self, package_id: u32) -> std::io::Result<Box<dyn ReadSeek>>;
+
+    // The raw contents of the meta file.
+    fn read_meta(&self) -> std::io::Result<Vec<u8>>;
+}
+
+// The usual directory layout: the meta and `PADxxxxx.paz` packages side by side under `root`.
+#[derive(Debug, Clone)]
+pub struct FsSource {
+    root: PathBuf,
+    meta_file: PathBuf,
+}
+
+impl FsSource {
+    pub fn new(root: &Path) -> Self {
+        Self::with_meta(root, Path::new(crate::DEFAULT_META_FILE))
+    }
+
+    // A relative `meta_file` is resolved against `root`, as in `MetaFile::new_with_meta`.
+    pub fn with_meta(root: &Path, meta_file: &Path) -> Self {
+        FsSource {
+            root: root.to_path_buf(),
+            meta_file: meta_file.to_path_buf(),
+        }
+    }
+}
+
+impl PackageSource for FsSource {
+    fn open(&self, package_id: u32) -> std::io::Result<Box<dyn ReadSeek>> {
+        let paz_path = self.root.join(package_file_name(package_id));
+        Ok(Box::new(open_package(&paz_path, true)?))
+    }
+
+    fn read_meta(&self) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.root.join(&self.meta_file))
+    }
+}
+
+impl MetaFile {
+    // Parses the source's meta and reads every package through it. `root` is left empty.
+    pub fn from_source(source: impl PackageSource + 'static, key: &[u8]) -> Result<Self, PadError> {
+        let bytes = source.read_meta()?;
+        let mut meta = Self::from_bytes(bytes, PathBuf::new(), key)?;
+        meta.source = Some(Arc::new(source));
+        Ok(meta)
+    }
+
+    // Opens a package through `source` when set, or from `root` otherwise.
+    pub(crate) fn open_package_reader(
+        &self,
+        package_id: u32,
+    ) -> Result<Box<dyn ReadSeek>, PadError> {
+        let opened = match &self.source {
+            Some(source) => source.open(package_id),
+            None => {
+                let paz_path = self.root.join(package_file_name(package_id));
+                open_package(&paz_path, self.share_packages)
+                    .map(|f| Box::new(f) as Box<dyn ReadSeek>)
+            }
+        };
+        opened.map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => {
+                PadError::MissingPackage(self.root.join(package_file_name(package_id)))
+            }
+            _ => PadError::Io(e),
+        })
+    }
+
+    // `hash::hashlittle` over the whole package, streamed rather than read into memory.
+    pub(crate) fn package_hash(&self, package_id: u32) -> Result<u32, PadError> {
+        let mut reader = self.open_package_reader(package_id)?;
+        let len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+        Ok(hash::hashlittle(&mut reader, len, 0)?)
+    }
+
+    pub(crate) fn package_size(&self, package_id: u32) -> Result<u64, PadError> {
+        Ok(self
+            .open_package_reader(package_id)?
+            .seek(SeekFrom::End(0))?)
+    }
+
+    // Reads the first `len` bytes of the record's block through `open_package_reader`,
+    // checking the whole block fits the package.
+    pub(crate) fn read_reader_block(
+        &self,
+        record: &MetaRecord,
+        len: usize,
+        buf: &mut Vec<u8>,
+    ) -> Result<(), PadError> {
+        let mut reader = self.open_package_reader(record.package_id)?;
+        let package_size = reader.seek(SeekFrom::End(0))?;
+        check_block_end(
+            record.package_id,
+            record.package_offset as u64,
+            record.sz_compressed as u64,
+            package_size,
+        )?;
+        reader.seek(SeekFrom::Start(record.package_offset as u64))?;
+        buf.clear();
+        buf.resize(len, 0);
+        reader.read_exact(buf)?;
+        Ok(())
+    }
+}
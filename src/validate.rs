@@ -0,0 +1,224 @@
+// Consistency checks over the relationships between the meta, path, file and package tables.
+// Each validator stands alone and `full_report` gathers them into one summary.
+use crate::{MetaFile, MetaRecord, PadError};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+const REPORT_SAMPLES: usize = 10;
+
+#[derive(Debug)]
+pub struct PackageMismatch {
+    pub id: u32,
+    pub expected_size: u32,
+    // None when the package file is missing or unreadable.
+    pub actual_size: Option<u64>,
+}
+
+#[derive(Debug)]
+pub struct PackageChecksum {
+    pub id: u32,
+    pub stored: u32,
+    // None when the package file is missing or unreadable.
+    pub computed: Option<u32>,
+}
+
+#[derive(Debug)]
+pub struct ReportSection<T> {
+    pub count: usize,
+    pub samples: Vec<T>,
+}
+
+impl<T> ReportSection<T> {
+    fn new(offenders: Vec<T>) -> ReportSection<T> {
+        let count = offenders.len();
+        let mut samples = offenders;
+        samples.truncate(REPORT_SAMPLES);
+        ReportSection { count, samples }
+    }
+}
+
+#[derive(Debug)]
+pub struct ArchiveReport {
+    pub bad_path_ranges: ReportSection<usize>,
+    pub bad_file_ids: ReportSection<usize>,
+    pub orphan_records: ReportSection<usize>,
+    pub path_collisions: ReportSection<(PathBuf, Vec<usize>)>,
+    pub package_mismatches: ReportSection<PackageMismatch>,
+}
+
+impl ArchiveReport {
+    pub fn is_healthy(&self) -> bool {
+        self.bad_path_ranges.count == 0
+            && self.bad_file_ids.count == 0
+            && self.orphan_records.count == 0
+            && self.path_collisions.count == 0
+            && self.package_mismatches.count == 0
+    }
+}
+
+impl MetaFile {
+    // Path table indices whose bucket range is inverted or runs past the file table.
+    pub fn validate_path_ranges(&self) -> Vec<usize> {
+        self.install(|| {
+            self.path_table
+                .par_iter()
+                .enumerate()
+                .filter(|(_, pr)| {
+                    pr.file_range.start > pr.file_range.end
+                        || pr.file_range.end > self.file_table.len()
+                })
+                .map(|(i, _)| i)
+                .collect()
+        })
+    }
+
+    // Meta table indices whose `file_id` is past the end of the file table.
+    pub fn validate_file_ids(&self) -> Vec<usize> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .enumerate()
+                .filter(|(_, mr)| self.bad_file_id(mr))
+                .map(|(i, _)| i)
+                .collect()
+        })
+    }
+
+    // Meta table indices whose `path_id` bucket doesn't cover the record's own position, either
+    // because the bucket range is inconsistent or the `path_id` is out of range. The filters match
+    // each record by its own `path_id`, but anything walking `PathRecord::file_range` misses or
+    // misplaces these records. Only meaningful before filtering.
+    pub fn orphan_records(&self) -> Vec<usize> {
+        self.install(|| {
+            self.meta_table
+                .par_iter()
+                .enumerate()
+                .filter(|(i, mr)| self.is_orphan(*i, mr))
+                .map(|(i, _)| i)
+                .collect()
+        })
+    }
+
+    // Packages from the package table that are missing on disk or whose size differs from the
+    // size recorded in the meta.
+    pub fn verify_packages(&self) -> Vec<PackageMismatch> {
+        self.install(|| {
+            self.package_table
+                .par_iter()
+                .filter_map(|pr| {
+                    let actual_size = self.package_size(pr.id).ok();
+                    match actual_size {
+                        Some(size) if size == pr.size as u64 => None,
+                        _ => Some(PackageMismatch {
+                            id: pr.id,
+                            expected_size: pr.size,
+                            actual_size,
+                        }),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    // Checks a package's contents against the hash stored for it in the package table. The hash
+    // is taken to be lookup3 `hashlittle` with a zero seed over the whole file; that's unconfirmed,
+    // so `package_checksum` exposes both values for comparing against other tools.
+    pub fn verify_package(&self, package_id: u32) -> Result<bool, PadError> {
+        let checksum = self.package_checksum(package_id)?;
+        Ok(checksum.computed == Some(checksum.stored))
+    }
+
+    pub fn package_checksum(&self, package_id: u32) -> Result<PackageChecksum, PadError> {
+        let pr = self
+            .package_table
+            .iter()
+            .find(|pr| pr.id == package_id)
+            .ok_or(PadError::IndexOutOfRange {
+                table: "package",
+                index: package_id as usize,
+            })?;
+        Ok(PackageChecksum {
+            id: pr.id,
+            stored: pr.hash,
+            computed: Some(self.package_hash(pr.id)?),
+        })
+    }
+
+    // Packages whose contents don't hash to their stored value, including missing ones.
+    pub fn verify_all_packages(&self) -> Vec<PackageChecksum> {
+        self.install(|| {
+            self.package_table
+                .par_iter()
+                .filter_map(|pr| {
+                    let computed = self.package_hash(pr.id).ok();
+                    match computed {
+                        Some(hash) if hash == pr.hash => None,
+                        _ => Some(PackageChecksum {
+                            id: pr.id,
+                            stored: pr.hash,
+                            computed,
+                        }),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    // Logical paths (path + file name) shared by more than one meta record, along with the meta
+    // table indices of those records. Records with out of range ids are skipped.
+    pub fn find_path_collisions(&self) -> Vec<(PathBuf, Vec<usize>)> {
+        self.install(|| {
+            let resolved: Vec<(usize, PathBuf)> = self
+                .meta_table
+                .par_iter()
+                .enumerate()
+                .filter_map(|(i, mr)| Some((i, self.logical_path(mr).ok()?)))
+                .collect();
+            let mut by_path: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+            for (i, path) in resolved {
+                by_path.entry(path).or_default().push(i);
+            }
+            let mut collisions: Vec<_> = by_path
+                .into_iter()
+                .filter(|(_, indices)| indices.len() > 1)
+                .collect();
+            collisions.par_sort_by_key(|(_, indices)| indices[0]);
+            collisions
+        })
+    }
+
+    // Runs every validator. The per-record checks share a single pass over the meta table.
+    pub fn full_report(&self) -> ArchiveReport {
+        self.install(|| {
+            let (bad_file_ids, orphan_records): (Vec<_>, Vec<_>) = self
+                .meta_table
+                .par_iter()
+                .enumerate()
+                .map(|(i, mr)| {
+                    let bad_file_id = self.bad_file_id(mr).then_some(i);
+                    let orphan = self.is_orphan(i, mr).then_some(i);
+                    (bad_file_id, orphan)
+                })
+                .unzip();
+            ArchiveReport {
+                bad_path_ranges: ReportSection::new(self.validate_path_ranges()),
+                bad_file_ids: ReportSection::new(bad_file_ids.into_iter().flatten().collect()),
+                orphan_records: ReportSection::new(orphan_records.into_iter().flatten().collect()),
+                path_collisions: ReportSection::new(self.find_path_collisions()),
+                package_mismatches: ReportSection::new(self.verify_packages()),
+            }
+        })
+    }
+
+    fn bad_file_id(&self, mr: &MetaRecord) -> bool {
+        mr.file_id as usize >= self.file_table.len()
+    }
+
+    fn is_orphan(&self, i: usize, mr: &MetaRecord) -> bool {
+        match self.path_table.get(mr.path_id as usize) {
+            Some(pr) => !pr.file_range.contains(&i),
+            None => true,
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// A selection of meta table indices over a borrowed `MetaFile`. Filtering a view narrows its
+// index list instead of rebuilding the meta table, so nothing is cloned and several views can
+// share one loaded archive.
+use crate::{
+    ExtractOptions, ExtractStats, FilterOptions, FilterStats, MetaFile, MetaRecord, PadError,
+    ReadLevel,
+};
+use rayon::prelude::*;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+#[derive(Debug, Clone)]
+pub struct MetaView<'a> {
+    meta: &'a MetaFile,
+    indices: Vec<usize>,
+}
+
+impl MetaFile {
+    // A view selecting every record of the current meta table.
+    pub fn view(&self) -> MetaView<'_> {
+        MetaView {
+            meta: self,
+            indices: (0..self.meta_table.len()).collect(),
+        }
+    }
+}
+
+impl<'a> MetaView<'a> {
+    // Meta table indices of the selected records, in meta table order.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn records(&self) -> impl Iterator<Item = &'a MetaRecord> + '_ {
+        self.indices.iter().map(|&i| &self.meta.meta_table[i])
+    }
+
+    // Keeps the records whose directory path matches. Each path is matched once and records are
+    // kept by their `path_id`, as `MetaFile::filter_by_path` does.
+    pub fn filter_by_path(&mut self, re_pat: &str) -> Result<FilterStats, PadError> {
+        self.filter_by_path_with(re_pat, &FilterOptions::default())
+    }
+
+    pub fn filter_by_path_with(
+        &mut self,
+        re_pat: &str,
+        options: &FilterOptions,
+    ) -> Result<FilterStats, PadError> {
+        let re = options.regex(re_pat)?;
+        let matches = self.meta.path_matcher(|path| re.is_match(path));
+        Ok(self.retain(matches))
+    }
+
+    pub fn filter_by_file(&mut self, re_pat: &str) -> Result<FilterStats, PadError> {
+        self.filter_by_file_with(re_pat, &FilterOptions::default())
+    }
+
+    pub fn filter_by_file_with(
+        &mut self,
+        re_pat: &str,
+        options: &FilterOptions,
+    ) -> Result<FilterStats, PadError> {
+        let re = options.regex(re_pat)?;
+        let matches = self.meta.file_matcher(|name| re.is_match(name));
+        Ok(self.retain(matches))
+    }
+
+    fn retain(&mut self, is_match: impl Fn(&MetaRecord) -> bool + Sync) -> FilterStats {
+        let before = self.indices.len();
+        let meta = self.meta;
+        self.indices = meta.install(|| {
+            self.indices
+                .par_iter()
+                .copied()
+                .filter(|&i| is_match(&meta.meta_table[i]))
+                .collect()
+        });
+        FilterStats::new(before, self.indices.len())
+    }
+
+    // Reads the record at meta table index `index`, whether or not it's selected.
+    pub fn read(&self, index: usize, level: &ReadLevel) -> Result<Vec<u8>, PadError> {
+        let record = self
+            .meta
+            .meta_table
+            .get(index)
+            .ok_or(PadError::IndexOutOfRange {
+                table: "meta",
+                index,
+            })?;
+        self.meta.read(record, level)
+    }
+
+    pub fn extract(
+        &self,
+        index: usize,
+        level: &ReadLevel,
+        out_path: &Path,
+    ) -> Result<(), PadError> {
+        let record = self
+            .meta
+            .meta_table
+            .get(index)
+            .ok_or(PadError::IndexOutOfRange {
+                table: "meta",
+                index,
+            })?;
+        self.meta.extract(record, level, out_path)
+    }
+
+    // `MetaFile::extract_many_with_options` over the selected records.
+    pub fn extract_many(
+        &self,
+        level: &ReadLevel,
+        out_path: &Path,
+        options: &ExtractOptions,
+    ) -> Result<ExtractStats, PadError> {
+        let records: Vec<_> = self.records().collect();
+        let cancel = AtomicBool::new(false);
+        self.meta
+            .extract_many_impl(&records, level, out_path, options, &cancel, |_, _, _| ())
+    }
+}
@@ -0,0 +1,1063 @@
+use ice::icefast::Ice;
+use pad::{MetaFile, MetaRecord, PathRecord, ReadLevel};
+use std::io::Write;
+use std::path::PathBuf;
+
+const KEY: &[u8; 8] = &[0x51, 0xF3, 0x0F, 0x11, 0x04, 0x24, 0x6A, 0x00];
+
+// Builds a meta file rooted in a scratch directory holding a single package, PAD00001.paz,
+// with one record per (path, file, bytes) entry laid out back to back.
+fn fixture(name: &str, entries: &[(&str, &str, &[u8])]) -> MetaFile {
+    let root = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+    let _ = std::fs::remove_dir_all(&root);
+    std::fs::create_dir_all(&root).expect("create fixture root failed");
+
+    let mut package = Vec::new();
+    let mut meta_table = Vec::new();
+    let mut path_table = Vec::new();
+    let mut file_table = Vec::new();
+    for (i, (path, file, bytes)) in entries.iter().enumerate() {
+        meta_table.push(MetaRecord {
+            hash: i as u32,
+            path_id: i as u32,
+            file_id: i as u32,
+            package_id: 1,
+            package_offset: package.len() as u32,
+            sz_compressed: bytes.len() as u32,
+            sz_original: bytes.len() as u32,
+        });
+        path_table.push(PathRecord {
+            path: PathBuf::from(path),
+            file_range: i..i + 1,
+        });
+        file_table.push(PathBuf::from(file));
+        package.extend_from_slice(bytes);
+    }
+    std::fs::write(root.join("PAD00001.paz"), package).expect("write package failed");
+
+    // A meta with no packages, records, paths or files, given the tables built above.
+    let empty = [0u32; 5].iter().flat_map(|n| n.to_le_bytes()).collect();
+    let mut meta = MetaFile::from_bytes(empty, root, KEY).expect("empty meta parsing error");
+    meta.meta_table = meta_table;
+    meta.path_table = path_table.into();
+    meta.file_table = file_table.into();
+    meta
+}
+
+#[test]
+fn extract_creates_parent_dirs() {
+    // extract should work standalone without extract_many pre-creating the directories.
+    let meta = fixture("extract_creates_parent_dirs", &[("a/b/c/d/", "deep.bin", b"deep bytes")]);
+    let out = meta.root.join("out");
+    let record = meta.meta_table.first().unwrap();
+    meta.extract(record, &ReadLevel::Raw, &out).expect("extract failed");
+    let written = std::fs::read(out.join("a/b/c/d/deep.bin")).expect("output missing");
+    assert_eq!(written, b"deep bytes", "output bytes mismatch");
+
+    // Extracting again into the existing directories is fine.
+    meta.extract(record, &ReadLevel::Raw, &out).expect("re-extract failed");
+}
+
+#[test]
+fn suspicious_size() {
+    // A bogus original size above the limit should error instead of being allocated.
+    let mut meta = fixture("suspicious_size", &[("a/", "big.bin", b"not really big")]);
+    meta.meta_table[0].sz_original = u32::MAX;
+    let record = meta.meta_table.first().unwrap();
+    let err = meta.read(record, &ReadLevel::Decompress).expect_err("oversized read succeeded");
+    assert!(matches!(err, pad::PadError::SuspiciousSize { .. }), "unexpected error: {}", err);
+
+    // Levels that don't decompress aren't affected and the limit is overridable.
+    meta.read(record, &ReadLevel::Raw).expect("raw read failed");
+    meta.max_decompressed_size = 16;
+    meta.meta_table[0].sz_original = 17;
+    let record = meta.meta_table.first().unwrap();
+    let err = meta.read(record, &ReadLevel::Decompress).expect_err("oversized read succeeded");
+    assert!(matches!(err, pad::PadError::SuspiciousSize { .. }), "unexpected error: {}", err);
+}
+
+#[test]
+fn size_mismatch() {
+    // A block decoding short of sz_original is an error rather than a short file. dbss blocks
+    // aren't encrypted so the stored quicklz block can be written as is.
+    let block = [&[0x46, 12, 0, 0, 0, 3, 0, 0, 0][..], b"abc"].concat();
+    let mut meta = fixture("size_mismatch", &[("a/", "short.dbss", &block)]);
+    meta.meta_table[0].sz_original = 20;
+    let record = meta.meta_table.first().unwrap();
+    match meta.read(record, &ReadLevel::Decompress) {
+        Err(pad::PadError::SizeMismatch { expected, got }) => assert_eq!((expected, got), (20, 3), "sizes mismatch"),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("short block decoded"),
+    }
+    let out = meta.root.join("out");
+    assert!(meta.extract(record, &ReadLevel::Decompress, &out).is_err(), "short block extracted");
+}
+
+#[test]
+fn unencrypted_extensions() {
+    // The unencrypted extension check ignores case and takes extensions added by the caller.
+    let block = [&[0x46, 12, 0, 0, 0, 3, 0, 0, 0][..], b"abc"].concat();
+    let mut meta = fixture(
+        "unencrypted_extensions",
+        &[("a/", "upper.DBSS", &block), ("a/", "other.raw", &block)],
+    );
+    meta.meta_table.iter_mut().for_each(|record| record.sz_original = 3);
+    let read = |meta: &MetaFile, i: usize, level| meta.read(&meta.meta_table[i], level).expect("read error");
+    assert_eq!(read(&meta, 0, &ReadLevel::Decrypt), block, "DBSS block was decrypted");
+    assert_eq!(read(&meta, 0, &ReadLevel::Decompress), b"abc", "DBSS content mismatch");
+    assert_ne!(read(&meta, 1, &ReadLevel::Decrypt), block, "raw block wasn't decrypted");
+    meta.unencrypted_extensions.push("RAW".to_string());
+    assert_eq!(read(&meta, 1, &ReadLevel::Decrypt), block, "raw block was decrypted");
+    assert!(pad::is_unencrypted_name(std::path::Path::new("x.Dbss"), pad::DEFAULT_UNENCRYPTED_EXTENSIONS), "mixed case mismatch");
+    assert!(!pad::is_unencrypted_name(std::path::Path::new("x.dbss.bak"), pad::DEFAULT_UNENCRYPTED_EXTENSIONS), "suffix mismatch");
+}
+
+#[test]
+fn offset_overflow() {
+    // A block running past the end of its package is reported by every backend.
+    let mut meta = fixture("offset_overflow", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two")]);
+    meta.meta_table[1].package_offset = 4;
+    for backend in [pad::ReadBackend::File, pad::ReadBackend::CachedFile, pad::ReadBackend::Mmap] {
+        meta.backend = backend;
+        match meta.read(&meta.meta_table[1], &ReadLevel::Raw) {
+            Err(pad::PadError::OffsetOverflow { package_id, end, package_size }) => {
+                assert_eq!((package_id, end, package_size), (1, 7, 6), "{:?} overflow mismatch", backend)
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("{:?} read past the package", backend),
+        }
+    }
+
+    // A prefix that fits in the package is still refused when the whole block doesn't.
+    let mut meta = fixture("offset_overflow_prefix", &[("a/", "one.bin", &[1; 20]), ("a/", "two.bin", &[2; 20])]);
+    meta.meta_table[1].sz_compressed = 30;
+    for backend in [pad::ReadBackend::File, pad::ReadBackend::CachedFile, pad::ReadBackend::Mmap] {
+        meta.backend = backend;
+        let result = meta.read_prefix(&meta.meta_table[1], &ReadLevel::Raw, 4);
+        assert!(matches!(result, Err(pad::PadError::OffsetOverflow { .. })), "{:?} prefix read past the package", backend);
+    }
+}
+
+#[test]
+fn quicklz_detection() {
+    // A plain block that happens to start with 0x6E is returned as is, while a quicklz stored
+    // block padded out to the cipher block size is unwrapped.
+    let ice = Ice::new(0, KEY);
+    let mut plain = b"nothing quicklz in here.".to_vec();
+    ice.encrypt_par(&mut plain);
+    let mut stored = [&[0x6E, 12, 0, 0, 0, 3, 0, 0, 0][..], b"abc", &[0; 4]].concat();
+    ice.encrypt_par(&mut stored);
+    let mut meta = fixture("quicklz_detection", &[("a/", "plain.bin", &plain), ("a/", "stored.bin", &stored)]);
+    meta.meta_table[1].sz_original = 3;
+    let decoded = meta.read(&meta.meta_table[0], &ReadLevel::Decompress).expect("read failed");
+    assert_eq!(decoded, b"nothing quicklz in here.", "plain block mismatch");
+    let decoded = meta.read(&meta.meta_table[1], &ReadLevel::Decompress).expect("read failed");
+    assert_eq!(decoded, b"abc", "stored block mismatch");
+    assert_eq!(meta.compression_of(&meta.meta_table[0]).unwrap(), pad::Compression::None, "plain compression mismatch");
+    assert_eq!(meta.compression_of(&meta.meta_table[1]).unwrap(), pad::Compression::QuickLz, "stored compression mismatch");
+}
+
+#[test]
+fn block_info() {
+    // The header sizes of a quicklz block, and the record's sizes for an uncompressed one.
+    let ice = Ice::new(0, KEY);
+    let mut plain = b"nothing quicklz in here.".to_vec();
+    ice.encrypt_par(&mut plain);
+    let mut stored = [&[0x6E, 12, 0, 0, 0, 3, 0, 0, 0][..], b"abc", &[0; 4]].concat();
+    ice.encrypt_par(&mut stored);
+    let mut meta = fixture("block_info", &[("a/", "plain.bin", &plain), ("a/", "stored.bin", &stored)]);
+    meta.meta_table[1].sz_original = 3;
+    let info = meta.block_info(&meta.meta_table[0]).expect("block info failed");
+    let expected = pad::BlockInfo { compressed: false, sz_compressed: 24, sz_original: 24, first_byte: Some(b'n') };
+    assert_eq!(info, expected, "plain block info mismatch");
+    let info = meta.block_info(&meta.meta_table[1]).expect("block info failed");
+    let expected = pad::BlockInfo { compressed: true, sz_compressed: 12, sz_original: 3, first_byte: Some(0x6E) };
+    assert_eq!(info, expected, "stored block info mismatch");
+}
+
+#[test]
+fn read_streaming() {
+    // Streaming writes the same bytes `read` returns at every level.
+    let ice = Ice::new(0, KEY);
+    let mut plain = b"nothing quicklz in here.".to_vec();
+    ice.encrypt_par(&mut plain);
+    let mut stored = [&[0x6E, 12, 0, 0, 0, 3, 0, 0, 0][..], b"abc", &[0; 4]].concat();
+    ice.encrypt_par(&mut stored);
+    let mut meta = fixture("read_streaming", &[("a/", "plain.bin", &plain), ("a/", "stored.bin", &stored)]);
+    meta.meta_table[0].sz_original = 20;
+    meta.meta_table[1].sz_original = 3;
+    for record in &meta.meta_table {
+        for level in [ReadLevel::Raw, ReadLevel::Decrypt, ReadLevel::Decompress] {
+            let mut streamed = Vec::new();
+            let written = meta.read_streaming(record, &level, &mut streamed).expect("streaming read failed");
+            assert_eq!(streamed, meta.read(record, &level).unwrap(), "streamed bytes mismatch");
+            assert_eq!(written, streamed.len() as u64, "written count mismatch");
+        }
+    }
+    meta.meta_table[1].sz_original = 4;
+    let err = meta.read_streaming(&meta.meta_table[1], &ReadLevel::Decompress, &mut Vec::new()).expect_err("size mismatch");
+    assert!(matches!(err, pad::PadError::SizeMismatch { expected: 4, got: 3 }), "unexpected error: {}", err);
+}
+
+#[test]
+fn read_many() {
+    // Every record is decoded in table order and a failure stays with its own record.
+    let mut meta = fixture("read_many", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two"), ("a/", "three.bin", b"three")]);
+    meta.meta_table[1].package_offset = 100;
+    let read = meta.read_many(&ReadLevel::Raw);
+    assert_eq!(read.iter().map(|(i, _)| *i).collect::<Vec<_>>(), [0, 1, 2], "indices mismatch");
+    assert_eq!(read[0].1.as_ref().unwrap(), b"one", "first bytes mismatch");
+    assert!(matches!(read[1].1, Err(pad::PadError::OffsetOverflow { .. })), "unexpected result");
+    assert_eq!(read[2].1.as_ref().unwrap(), b"three", "last bytes mismatch");
+}
+
+#[test]
+fn package_cache() {
+    // One caller owned cache serves several metas, holding no more packages than its limit.
+    let first = fixture("package_cache_first", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two")]);
+    let second = fixture("package_cache_second", &[("b/", "three.bin", b"three")]);
+    for cache in [pad::PackageCache::files(1), pad::PackageCache::mapped(1)] {
+        for meta in [&first, &second, &first] {
+            for record in &meta.meta_table {
+                let read = meta.read_with(record, &ReadLevel::Raw, &cache).expect("cached read failed");
+                assert_eq!(read, meta.read(record, &ReadLevel::Raw).unwrap(), "cached bytes mismatch");
+            }
+            assert_eq!(cache.len(), 1, "held package count mismatch");
+        }
+        cache.clear();
+        assert!(cache.is_empty(), "cache not cleared");
+    }
+}
+
+#[test]
+fn compression_report() {
+    // The report comes from the size fields alone; empty originals stay out of the ratios.
+    let mut meta = fixture("compression_report", &[("a/", "small.bin", b"abcd"), ("a/", "stored.bin", b"12345678"), ("a/", "empty.bin", b"xyz")]);
+    meta.meta_table[0].sz_original = 16;
+    meta.meta_table[2].sz_original = 0;
+    let report = meta.compression_report();
+    assert_eq!((report.record_count, report.stored_count), (3, 2), "counts mismatch");
+    assert_eq!((report.total_compressed, report.total_original), (15, 24), "totals mismatch");
+    assert_eq!((report.overall_ratio, report.mean_ratio), (0.625, 0.625), "ratios mismatch");
+    assert_eq!(report.best.map(|mr| mr.hash), Some(0), "best record mismatch");
+    assert_eq!(report.worst.map(|mr| mr.hash), Some(1), "worst record mismatch");
+
+    meta.meta_table.clear();
+    let report = meta.compression_report();
+    assert_eq!((report.record_count, report.overall_ratio), (0, 0.0), "empty report mismatch");
+    assert!(report.best.is_none() && report.worst.is_none(), "empty extremes mismatch");
+}
+
+#[test]
+fn read_prefix() {
+    // A prefix is the start of what `read` returns at every level, however short or long.
+    let ice = Ice::new(0, KEY);
+    let mut plain = b"nothing quicklz in here, just a longer plain block.".to_vec();
+    ice.encrypt_par(&mut plain);
+    let mut stored = [&[0x6E, 40, 0, 0, 0, 31, 0, 0, 0][..], b"stored quicklz block payload...", &[0; 4]].concat();
+    ice.encrypt_par(&mut stored);
+    let mut meta = fixture("read_prefix", &[("a/", "plain.bin", &plain), ("a/", "stored.bin", &stored)]);
+    meta.meta_table[0].sz_original = 40;
+    meta.meta_table[1].sz_original = 31;
+    for record in &meta.meta_table {
+        for level in [ReadLevel::Raw, ReadLevel::Decrypt, ReadLevel::Decompress] {
+            let full = meta.read(record, &level).unwrap();
+            for max_len in [0, 1, 5, 16, 30, 100, usize::MAX] {
+                let prefix = meta.read_prefix(record, &level, max_len).expect("prefix read failed");
+                assert_eq!(prefix, full[..max_len.min(full.len())], "prefix bytes mismatch");
+            }
+        }
+    }
+}
+
+#[test]
+fn sniff_type() {
+    // Types come from the decoded prefix, with caller signatures checked first.
+    let ice = Ice::new(0, KEY);
+    let blocks: Vec<Vec<u8>> = [&b"\x89PNG\r\n\x1A\n image data"[..], b"<?xml version=\"1.0\"?>", b"PAR mesh data...", b"nothing known"]
+        .iter()
+        .map(|bytes| {
+            let mut block = bytes.to_vec();
+            ice.encrypt_par(&mut block);
+            block
+        })
+        .collect();
+    let meta = fixture(
+        "sniff_type",
+        &[("a/", "image", &blocks[0]), ("a/", "doc", &blocks[1]), ("a/", "mesh", &blocks[2]), ("a/", "other", &blocks[3])],
+    );
+    let types: Vec<_> = meta.meta_table.iter().map(|mr| meta.sniff_type(mr)).collect();
+    assert_eq!(types, [Some("png"), Some("xml"), None, None], "sniffed types mismatch");
+    let pac = pad::sniff::Signature { extension: "pac", offset: 0, magic: b"PAR " };
+    assert_eq!(meta.sniff_type_with(&meta.meta_table[2], &[pac]), Some("pac"), "extra signature mismatch");
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn async_reads() {
+    // The async calls decode and write the same bytes as their blocking counterparts.
+    let ice = Ice::new(0, KEY);
+    let mut block = b"read on a tokio runtime".to_vec();
+    ice.encrypt_par(&mut block);
+    let meta = std::sync::Arc::new(fixture("async_reads", &[("a/", "one.bin", &block)]));
+    let record = &meta.meta_table[0];
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    for level in [ReadLevel::Raw, ReadLevel::Decrypt] {
+        let expected = meta.read(record, &level).unwrap();
+        let read = runtime.block_on(meta.read_async(record, level)).expect("async read failed");
+        assert_eq!(read, expected, "async bytes mismatch");
+    }
+    let out = meta.root.join("out");
+    let written = runtime.block_on(meta.extract_async(record, ReadLevel::Decrypt, &out)).expect("async extract failed");
+    assert_eq!(written, block.len() as u64, "written count mismatch");
+    assert_eq!(std::fs::read(out.join("a/one.bin")).unwrap(), b"read on a tokio runtime", "extracted bytes mismatch");
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn digest() {
+    // Digests are SHA-256 of the decoded bytes, listed by logical path.
+    let meta = fixture("digest", &[("a/", "abc.bin", b"abc"), ("b/", "empty.bin", b"")]);
+    let abc = [
+        0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23, 0xb0, 0x03,
+        0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+    ];
+    let digest = meta.digest(&meta.meta_table[0], &ReadLevel::Raw).expect("digest failed");
+    assert_eq!(digest, abc, "digest mismatch");
+    let all = meta.digest_all(&ReadLevel::Raw).expect("digest_all failed");
+    assert_eq!(all.len(), 2, "digest count mismatch");
+    assert_eq!(all[0], (PathBuf::from("a/abc.bin"), abc), "digest_all mismatch");
+    assert_eq!(all[1].0, PathBuf::from("b/empty.bin"), "digest_all path mismatch");
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd_blocks() {
+    // A zstd frame padded out to the cipher block size is detected and decompressed.
+    let original = b"zstd zstd zstd zstd zstd zstd zstd zstd".to_vec();
+    let mut block = zstd::bulk::compress(&original, 3).unwrap();
+    block.resize(block.len().div_ceil(8) * 8, 0);
+    Ice::new(0, KEY).encrypt_par(&mut block);
+    let mut meta = fixture("zstd_blocks", &[("a/", "frame.bin", &block)]);
+    meta.meta_table[0].sz_original = original.len() as u32;
+    let record = &meta.meta_table[0];
+    assert_eq!(meta.compression_of(record).unwrap(), pad::Compression::Zstd, "compression mismatch");
+    assert_eq!(meta.read(record, &ReadLevel::Decompress).expect("read failed"), original, "decoded bytes mismatch");
+    let mut streamed = Vec::new();
+    meta.read_streaming(record, &ReadLevel::Decompress, &mut streamed).expect("streaming read failed");
+    assert_eq!(streamed, original, "streamed bytes mismatch");
+    let prefix = meta.read_prefix(record, &ReadLevel::Decompress, 7).expect("prefix read failed");
+    assert_eq!(prefix, original[..7], "prefix bytes mismatch");
+}
+
+#[test]
+fn lowercase_paths() {
+    // Paths colliding once lowercased keep the first record in meta table order.
+    let meta = fixture(
+        "lowercase_paths",
+        &[("Dir/", "File.BIN", b"first"), ("dir/", "file.bin", b"second"), ("dir/", "Other.bin", b"other")],
+    );
+    let out = meta.root.join("out");
+    let options = pad::ExtractOptions {
+        lowercase_paths: true,
+        ..Default::default()
+    };
+    let stats = meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert_eq!(stats.collisions, [(PathBuf::from("dir/file.bin"), 1)], "collisions mismatch");
+    assert_eq!(std::fs::read(out.join("dir/file.bin")).unwrap(), b"first", "collision winner mismatch");
+    assert_eq!(std::fs::read(out.join("dir/other.bin")).unwrap(), b"other", "lowercased output mismatch");
+
+    // A single record is written where `extract_many_with_options` puts it.
+    let out = meta.root.join("single");
+    meta.extract_with_options(&meta.meta_table[2], &ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert_eq!(std::fs::read(out.join("dir/other.bin")).unwrap(), b"other", "single lowercased output mismatch");
+}
+
+#[test]
+fn fix_extensions() {
+    // Sniffed content corrects missing or wrong extensions and leaves matching ones alone.
+    let meta = fixture(
+        "fix_extensions",
+        &[
+            ("a/", "texture", b"DDS |header"),
+            ("a/", "doc.txt", b"<?xml version=\"1.0\"?>"),
+            ("a/", "already.XML", b"<?xml version=\"1.0\"?>"),
+            ("a/", "plain.txt", b"plain text"),
+        ],
+    );
+    let out = meta.root.join("out");
+    let options = pad::ExtractOptions {
+        fix_extensions: true,
+        write_manifest: true,
+        order: pad::ExtractOrder::Table,
+        ..Default::default()
+    };
+    meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert!(out.join("a/texture.dds").exists(), "missing extension not added");
+    assert!(out.join("a/doc.xml").exists(), "wrong extension not corrected");
+    assert!(out.join("a/already.XML").exists(), "matching extension changed");
+    assert!(out.join("a/plain.txt").exists(), "unknown content renamed");
+    // The manifest lists the paths the files were written to.
+    let sidecar = std::fs::read_to_string(out.join(pad::MANIFEST_SIDECAR)).expect("sidecar missing");
+    let paths: Vec<_> = sidecar.lines().filter_map(|l| l.split('"').nth(3)).collect();
+    assert_eq!(paths, ["a/texture.dds", "a/doc.xml", "a/already.XML", "a/plain.txt"], "manifest paths mismatch");
+}
+
+#[test]
+fn fix_extensions_padded_quicklz() {
+    // A still wrapped block padded out to the cipher block size is sniffed as quicklz, as
+    // decoding would detect it.
+    let ice = Ice::new(0, KEY);
+    let mut stored = [&[0x6E, 12, 0, 0, 0, 3, 0, 0, 0][..], b"abc", &[0; 4]].concat();
+    ice.encrypt_par(&mut stored);
+    let meta = fixture("fix_extensions_padded_quicklz", &[("a/", "stored.bin", &stored)]);
+    let out = meta.root.join("out");
+    let options = pad::ExtractOptions { fix_extensions: true, ..Default::default() };
+    meta.extract_many_with_options(&ReadLevel::Decrypt, &out, &options).expect("extract failed");
+    assert!(out.join("a/stored.qlz").exists(), "padded quicklz block not sniffed");
+}
+
+#[test]
+fn extract_to_writer() {
+    // Writing into an arbitrary sink yields the same bytes as extracting to disk.
+    let meta = fixture("extract_to_writer", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two bytes")]);
+    let mut sink = Vec::new();
+    for record in &meta.meta_table {
+        let written = meta.extract_to_writer(record, &ReadLevel::Raw, &mut sink).expect("extract failed");
+        assert_eq!(written, record.sz_compressed as u64, "bytes written mismatch");
+    }
+    assert_eq!(sink, b"onetwo bytes", "sink contents mismatch");
+}
+
+#[test]
+fn unsafe_paths() {
+    // Archive paths that would escape the output directory are refused.
+    let meta = fixture(
+        "unsafe_paths",
+        &[("../escape/", "evil.bin", b"evil"), ("/abs/", "evil.bin", b"evil"), ("a/", "../../evil.bin", b"evil")],
+    );
+    let out = meta.root.join("out");
+    for record in &meta.meta_table {
+        match meta.extract(record, &ReadLevel::Raw, &out) {
+            Err(pad::PadError::UnsafePath(_)) => (),
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(()) => panic!("unsafe path extracted"),
+        }
+    }
+    meta.extract_many(&ReadLevel::Raw, &out).expect("extract failed");
+    assert!(!meta.root.join("escape").exists(), "escaped the output directory");
+    assert!(!meta.root.join("evil.bin").exists(), "escaped the output directory");
+}
+
+#[test]
+fn extract_failures() {
+    // A record from a missing package is reported while the others are still extracted, or
+    // ends the run when failing fast.
+    let mut meta = fixture(
+        "extract_failures",
+        &[("a/", "one.bin", b"one"), ("a/", "lost.bin", b"lost"), ("a/", "two.bin", b"two")],
+    );
+    meta.meta_table[1].package_id = 2;
+    let out = meta.root.join("out");
+    let stats = meta.extract_many(&ReadLevel::Raw, &out).expect("extract failed");
+    assert_eq!(stats.files, 2, "extracted count mismatch");
+    assert_eq!(stats.failures.len(), 1, "failure count mismatch");
+    assert_eq!(stats.failures[0].0, meta.meta_table[1].hash, "failed record mismatch");
+    assert_eq!(std::fs::read(out.join("a/two.bin")).unwrap(), b"two", "output bytes mismatch");
+
+    let options = pad::ExtractOptions { fail_fast: true, ..Default::default() };
+    let err = meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect_err("failure ignored");
+    assert!(matches!(err, pad::PadError::Io(_)), "unexpected error: {}", err);
+}
+
+#[test]
+fn extract_raw() {
+    // Raw extraction writes the stored block verbatim, as a direct read of the package gives.
+    let meta = fixture("extract_raw", &[("a/", "one.bin", b"first block"), ("a/", "two.bin", b"second block")]);
+    let out = meta.root.join("out");
+    let record = &meta.meta_table[1];
+    meta.extract(record, &ReadLevel::Raw, &out).expect("extract failed");
+
+    use std::io::{Read, Seek, SeekFrom};
+    let mut package = std::fs::File::open(meta.package_path(record)).unwrap();
+    package.seek(SeekFrom::Start(record.package_offset as u64)).unwrap();
+    let mut block = vec![0; record.sz_compressed as usize];
+    package.read_exact(&mut block).unwrap();
+    assert_eq!(std::fs::read(out.join("a/two.bin")).unwrap(), block, "raw bytes mismatch");
+}
+
+#[test]
+fn overwrite_policy() {
+    // Existing files are kept or replaced according to the policy and counted as skipped.
+    let meta = fixture("overwrite_policy", &[("a/", "same.bin", b"same"), ("a/", "diff.bin", b"different")]);
+    let out = meta.root.join("out");
+    std::fs::create_dir_all(out.join("a")).unwrap();
+    std::fs::write(out.join("a/same.bin"), b"SAME").unwrap();
+    std::fs::write(out.join("a/diff.bin"), b"old").unwrap();
+
+    let options = pad::ExtractOptions { overwrite: pad::Overwrite::Never, ..Default::default() };
+    let stats = meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert_eq!((stats.files, stats.skipped), (0, 2), "never counts mismatch");
+    assert_eq!(std::fs::read(out.join("a/diff.bin")).unwrap(), b"old", "file overwritten");
+
+    let options = pad::ExtractOptions { overwrite: pad::Overwrite::IfDifferentSize, ..Default::default() };
+    let stats = meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert_eq!((stats.files, stats.skipped), (1, 1), "size counts mismatch");
+    assert_eq!(std::fs::read(out.join("a/same.bin")).unwrap(), b"SAME", "same size file overwritten");
+    assert_eq!(std::fs::read(out.join("a/diff.bin")).unwrap(), b"different", "different size file kept");
+
+    let written = meta
+        .extract_with_options(&meta.meta_table[0], &ReadLevel::Raw, &out, &pad::ExtractOptions::default())
+        .expect("extract failed");
+    assert!(written, "always policy skipped");
+    assert_eq!(std::fs::read(out.join("a/same.bin")).unwrap(), b"same", "file not overwritten");
+}
+
+#[test]
+fn exclusion_filters() {
+    // Excluded directories and names aren't extracted and everything else is.
+    let mut meta = fixture(
+        "exclusion_filters",
+        &[("loc/en/", "strings.xml", b"en"), ("ui/", "icon.dds", b"icon"), ("ui/", "icon.dds.bak", b"bak"), ("ui/", "menu.xml", b"menu")],
+    );
+    meta.filter_out_path("^loc/").expect("path filter error");
+    meta.filter_out_file(r"\.bak$").expect("file filter error");
+    let out = meta.root.join("out");
+    let stats = meta.extract_many(&ReadLevel::Raw, &out).expect("extract failed");
+    assert_eq!(stats.files, 2, "extracted count mismatch");
+    assert!(!out.join("loc").exists(), "excluded path extracted");
+    assert!(!out.join("ui/icon.dds.bak").exists(), "excluded file extracted");
+    assert!(out.join("ui/icon.dds").exists(), "kept file missing");
+    assert!(out.join("ui/menu.xml").exists(), "kept file missing");
+}
+
+#[test]
+fn exclusion_filter_options() {
+    // Case-insensitive exclusion drops what case-insensitive inclusion keeps.
+    let files: &[(&str, &str, &[u8])] = &[("Loc/", "strings.XML", b"en"), ("ui/", "menu.xml", b"menu"), ("ui/", "icon.dds", b"icon")];
+    let options = pad::FilterOptions { case_insensitive: true };
+    let mut meta = fixture("exclusion_filter_options", files);
+    let stats = meta.filter_out_path_with("^loc/", &options).expect("path filter error");
+    assert_eq!((stats.before, stats.after), (3, 2), "path exclusion mismatch");
+    let mut kept = meta.clone_filtered(None, None).expect("clone error");
+    kept.filter_by_file_with(r"\.xml$", &options).expect("file filter error");
+    let stats = meta.filter_out_file_with(r"\.xml$", &options).expect("file filter error");
+    assert_eq!(stats.removed, kept.meta_table.len(), "file exclusion mismatch");
+    assert_eq!(meta.list(), ["ui/icon.dds"], "kept records mismatch");
+}
+
+#[test]
+fn plan() {
+    // The plan lists what extract writes, skipping unsafe paths, and writes nothing itself.
+    let meta = fixture("plan", &[("a/b/", "one.bin", b"one"), ("../", "bad.bin", b"bad"), ("a/", "two.bin", b"two!")]);
+    let out = meta.root.join("out");
+    let planned = meta.plan(&out);
+    assert!(!out.exists(), "plan touched the disk");
+    assert_eq!(
+        planned,
+        [
+            pad::PlannedFile { out_path: out.join("a/b/one.bin"), sz_original: 3, package_id: 1 },
+            pad::PlannedFile { out_path: out.join("a/two.bin"), sz_original: 4, package_id: 1 },
+        ],
+        "plan mismatch"
+    );
+    meta.extract_many(&ReadLevel::Raw, &out).expect("extract failed");
+    assert!(planned.iter().all(|p| p.out_path.exists()), "planned file missing");
+}
+
+#[test]
+fn out_of_range_ids() {
+    // A record pointing past the file or path table is an error instead of a panic.
+    let meta = fixture("out_of_range_ids", &[("a/", "one.bin", b"one")]);
+    let out = meta.root.join("out");
+    let mut record = meta.meta_table[0].clone();
+    record.file_id = meta.file_table.len() as u32;
+    let err = meta.read(&record, &ReadLevel::Raw).expect_err("read past the file table");
+    assert!(
+        matches!(err, pad::PadError::IndexOutOfRange { table: "file", index: 1 }),
+        "unexpected error: {}",
+        err
+    );
+    let err = meta.extract(&record, &ReadLevel::Raw, &out).expect_err("extract past the file table");
+    assert!(matches!(err, pad::PadError::IndexOutOfRange { table: "file", .. }), "unexpected error: {}", err);
+    record.file_id = 0;
+    record.path_id = u32::MAX;
+    let err = meta.extract(&record, &ReadLevel::Raw, &out).expect_err("extract past the path table");
+    assert!(matches!(err, pad::PadError::IndexOutOfRange { table: "path", .. }), "unexpected error: {}", err);
+    assert!(!out.exists(), "out of range record wrote output");
+}
+
+#[test]
+fn out_of_range_ids_in_bulk() {
+    // A record with a bad id is reported as a failure without stopping the rest of the run.
+    let mut meta = fixture("out_of_range_ids_in_bulk", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two")]);
+    meta.meta_table[0].path_id = u32::MAX;
+    let out = meta.root.join("out");
+    let stats = meta.extract_many(&ReadLevel::Raw, &out).expect("extract failed");
+    assert_eq!(stats.files, 1, "extracted count mismatch");
+    assert_eq!(stats.failures.len(), 1, "failure count mismatch");
+    assert!(matches!(stats.failures[0].1, pad::PadError::IndexOutOfRange { table: "path", .. }), "unexpected error: {}", stats.failures[0].1);
+    assert_eq!(std::fs::read(out.join("a/two.bin")).unwrap(), b"two", "good record contents mismatch");
+}
+
+#[test]
+fn missing_packages() {
+    // Only packages referenced by the current selection are checked.
+    let mut meta = fixture("missing_packages", &[("a/", "one.bin", b"one"), ("b/", "two.bin", b"two")]);
+    assert!(meta.missing_packages().is_empty(), "missing packages mismatch");
+    meta.meta_table[1].package_id = 7;
+    assert_eq!(meta.missing_packages(), [(7, meta.root.join("PAD00007.paz"))], "missing packages mismatch");
+    meta.filter_by_file("one").expect("file filter error");
+    assert!(meta.missing_packages().is_empty(), "filtered missing packages mismatch");
+}
+
+#[test]
+fn thread_pool() {
+    // Extraction runs its parallel work on the supplied pool.
+    let mut meta = fixture("thread_pool", &[("a/", "one.bin", b"one"), ("b/", "two.bin", b"two")]);
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(3).build().expect("pool build failed");
+    meta.thread_pool = Some(std::sync::Arc::new(pool));
+    let on_pool = std::sync::atomic::AtomicBool::new(true);
+    let out = meta.root.join("out");
+    let stats = meta
+        .extract_many_with_progress(&ReadLevel::Raw, &out, |_| {
+            if rayon::current_num_threads() != 3 {
+                on_pool.store(false, std::sync::atomic::Ordering::Relaxed);
+            }
+        })
+        .expect("extract failed");
+    assert_eq!(stats.files, 2, "extracted count mismatch");
+    assert!(on_pool.into_inner(), "extraction left the pool");
+}
+
+#[test]
+fn find_collisions() {
+    // Records resolving to the same logical path are grouped by it.
+    let mut meta = fixture(
+        "find_collisions",
+        &[("a/", "one.bin", b"one"), ("b/", "two.bin", b"two"), ("a/", "one.bin", b"uno"), ("c/", "x.bin", b"x")],
+    );
+    assert_eq!(meta.find_collisions(), [(PathBuf::from("a/one.bin"), vec![0, 2])], "collisions mismatch");
+    assert_eq!(meta.find_path_collisions(), [(PathBuf::from("a/one.bin"), vec![0, 2])], "path collisions mismatch");
+    meta.meta_table[3].path_id = 1;
+    meta.meta_table[3].file_id = 1;
+    assert_eq!(meta.find_collisions().len(), 2, "collision count mismatch");
+    meta.filter_by_path("^a/").expect("path filter error");
+    assert_eq!(meta.find_collisions().len(), 1, "filtered collision count mismatch");
+}
+
+#[test]
+fn flat_layout() {
+    // Flat output names carry the package id, and names that would collide also carry the hash.
+    let meta = fixture(
+        "flat_layout",
+        &[("a/", "one.bin", b"one"), ("b/", "one.bin", b"uno"), ("c/d/", "two", b"two")],
+    );
+    let out = meta.root.join("out");
+    let options = pad::ExtractOptions {
+        layout: pad::OutputLayout::Flat { separator: "_".to_string() },
+        ..Default::default()
+    };
+    let stats = meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert_eq!(stats.files, 3, "extracted count mismatch");
+    let mut names: Vec<_> =
+        std::fs::read_dir(&out).unwrap().map(|e| e.unwrap().file_name().into_string().unwrap()).collect();
+    names.sort();
+    assert_eq!(names, ["1_one_00000000.bin", "1_one_00000001.bin", "1_two"], "flat names mismatch");
+    assert_eq!(std::fs::read(out.join("1_one_00000001.bin")).unwrap(), b"uno", "flat contents mismatch");
+
+    let single = meta.root.join("single");
+    meta.extract_with_options(&meta.meta_table[1], &ReadLevel::Raw, &single, &options).expect("extract failed");
+    assert_eq!(std::fs::read(single.join("1_one.bin")).unwrap(), b"uno", "single flat contents mismatch");
+}
+
+#[test]
+fn sanitize_names() {
+    // Names Windows refuses are rewritten when asked to and each rename is reported.
+    let meta = fixture(
+        "sanitize_names",
+        &[("con/", "aux.txt", b"aux"), ("a/", "what?.bin", b"what"), ("a/", "dots..", b"dots"), ("a/", "ok.bin", b"ok")],
+    );
+    let out = meta.root.join("out");
+    let options = pad::ExtractOptions { sanitize_names: pad::SanitizeNames::Always, ..Default::default() };
+    let mut stats = meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert_eq!(stats.files, 4, "extracted count mismatch");
+    stats.renamed.sort();
+    let expected = [("a/dots..", "a/dots__"), ("a/what?.bin", "a/what_.bin"), ("con/aux.txt", "con_/aux_.txt")]
+        .map(|(from, to)| (PathBuf::from(from), PathBuf::from(to)));
+    assert_eq!(stats.renamed, expected, "renamed mismatch");
+    assert_eq!(std::fs::read(out.join("con_/aux_.txt")).unwrap(), b"aux", "sanitized contents mismatch");
+    assert_eq!(std::fs::read(out.join("a/ok.bin")).unwrap(), b"ok", "untouched contents mismatch");
+    assert_eq!(pad::sanitize_windows_path(std::path::Path::new("lpt1/Nul.x")), PathBuf::from("lpt1_/Nul_.x"), "path mismatch");
+
+    let options = pad::ExtractOptions { sanitize_names: pad::SanitizeNames::Never, ..Default::default() };
+    let stats = meta.extract_many_with_options(&ReadLevel::Raw, &meta.root.join("raw"), &options).expect("extract failed");
+    assert!(stats.renamed.is_empty(), "names sanitized");
+}
+
+#[test]
+fn sanitize_collisions() {
+    // A name that sanitizes onto another is skipped instead of overwriting it, in `plan` too.
+    let meta = fixture("sanitize_collisions", &[("a/", "x?.bin", b"first"), ("a/", "x_.bin", b"second")]);
+    let out = meta.root.join("out");
+    let options = pad::ExtractOptions { sanitize_names: pad::SanitizeNames::Always, ..Default::default() };
+    let planned: Vec<_> = meta.plan_with_options(&out, &options).into_iter().map(|p| p.out_path).collect();
+    assert_eq!(planned, [out.join("a/x_.bin")], "planned paths mismatch");
+    let stats = meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    assert_eq!(stats.files, 1, "extracted count mismatch");
+    assert_eq!(stats.collisions, [(PathBuf::from("a/x_.bin"), 1)], "collisions mismatch");
+    assert_eq!(std::fs::read(out.join("a/x_.bin")).unwrap(), b"first", "kept contents mismatch");
+}
+
+#[test]
+fn manifest_sidecar() {
+    // The sidecar lists every output with where its block sits in the packages.
+    let meta = fixture("manifest_sidecar", &[("a/", "one.bin", b"one"), ("b/", "two.bin", b"two!")]);
+    let out = meta.root.join("out");
+    let options = pad::ExtractOptions { write_manifest: true, order: pad::ExtractOrder::Table, ..Default::default() };
+    meta.extract_many_with_options(&ReadLevel::Raw, &out, &options).expect("extract failed");
+    let sidecar = std::fs::read_to_string(out.join(pad::MANIFEST_SIDECAR)).expect("sidecar missing");
+    let expected = [
+        "[",
+        r#"  {"path": "a/one.bin", "hash": 0, "path_id": 0, "file_id": 0, "package_id": 1, "package_offset": 0, "sz_compressed": 3, "sz_original": 3},"#,
+        r#"  {"path": "b/two.bin", "hash": 1, "path_id": 1, "file_id": 1, "package_id": 1, "package_offset": 3, "sz_compressed": 4, "sz_original": 4}"#,
+        "]",
+    ];
+    assert_eq!(sidecar.lines().collect::<Vec<_>>(), expected, "sidecar mismatch");
+}
+
+// Packages held in memory, standing in for a remote store.
+#[derive(Debug)]
+struct MemorySource(std::collections::HashMap<u32, Vec<u8>>);
+
+impl pad::PackageSource for MemorySource {
+    fn open(&self, package_id: u32) -> std::io::Result<Box<dyn pad::ReadSeek>> {
+        match self.0.get(&package_id) {
+            Some(bytes) => Ok(Box::new(std::io::Cursor::new(bytes.clone()))),
+            None => Err(std::io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn read_meta(&self) -> std::io::Result<Vec<u8>> {
+        Err(std::io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[test]
+fn package_source() {
+    // Reads go through the source instead of the package files under `root`.
+    let mut meta = fixture("package_source", &[("a/", "one.bin", b"one"), ("b/", "two.bin", b"two")]);
+    let package = std::fs::read(meta.root.join("PAD00001.paz")).unwrap();
+    std::fs::remove_file(meta.root.join("PAD00001.paz")).unwrap();
+    meta.source = Some(std::sync::Arc::new(MemorySource([(1, package)].into())));
+    assert_eq!(meta.read(&meta.meta_table[1], &ReadLevel::Raw).expect("read failed"), b"two", "source read mismatch");
+    assert!(meta.missing_packages().is_empty(), "missing packages mismatch");
+    meta.meta_table[0].package_id = 2;
+    let err = meta.read(&meta.meta_table[0], &ReadLevel::Raw).expect_err("read from a missing package");
+    assert!(matches!(err, pad::PadError::MissingPackage(_)), "unexpected error: {}", err);
+    assert_eq!(meta.missing_packages().len(), 1, "missing packages mismatch");
+}
+
+#[test]
+fn extract_resumable() {
+    // A run interrupted after half the records resumes with the other half only.
+    let entries: &[(&str, &str, &[u8])] =
+        &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two"), ("b/", "three.bin", b"three"), ("b/", "four.bin", b"four")];
+    let mut meta = fixture("extract_resumable", entries);
+    let out = meta.root.join("out");
+    let journal = meta.root.join("journal");
+    let all = meta.meta_table.clone();
+    meta.meta_table.truncate(2);
+    let stats = meta.extract_many_resumable(&ReadLevel::Raw, &out, &journal).expect("extract failed");
+    assert_eq!(stats.files, 2, "first run count mismatch");
+    assert_eq!(std::fs::read_to_string(&journal).unwrap().lines().count(), 2, "journal len mismatch");
+
+    // Marks an output the resumed run mustn't touch, and a torn line it must ignore.
+    std::fs::write(out.join("a/one.bin"), b"kept").unwrap();
+    std::fs::OpenOptions::new().append(true).open(&journal).unwrap().write_all(b"0000").unwrap();
+    meta.meta_table = all;
+    let stats = meta.extract_many_resumable(&ReadLevel::Raw, &out, &journal).expect("resume failed");
+    assert_eq!(stats.files, 2, "resumed count mismatch");
+    assert_eq!(std::fs::read(out.join("a/one.bin")).unwrap(), b"kept", "journaled record rewritten");
+    assert_eq!(std::fs::read(out.join("b/four.bin")).unwrap(), b"four", "resumed contents mismatch");
+    let stats = meta.extract_many_resumable(&ReadLevel::Raw, &out, &journal).expect("resume failed");
+    assert_eq!(stats.files, 0, "completed run count mismatch");
+}
+
+#[test]
+fn meta_diff() {
+    // Paths are bucketed by presence, then by hash or original size.
+    let old = fixture(
+        "meta_diff_old",
+        &[("a/", "same.bin", b"same"), ("a/", "gone.bin", b"gone"), ("b/", "edit.bin", b"edit"), ("b/", "grow.bin", b"grow")],
+    );
+    let mut new = fixture(
+        "meta_diff_new",
+        &[("a/", "same.bin", b"same"), ("c/", "new.bin", b"new"), ("b/", "edit.bin", b"edit"), ("b/", "grow.bin", b"grown")],
+    );
+    new.meta_table[2].hash = 9;
+    let diff = new.diff(&old);
+    let expected = pad::MetaDiff {
+        added: vec![PathBuf::from("c/new.bin")],
+        removed: vec![PathBuf::from("a/gone.bin")],
+        changed: vec![PathBuf::from("b/edit.bin"), PathBuf::from("b/grow.bin")],
+    };
+    assert_eq!(diff, expected, "diff mismatch");
+    assert_eq!(new.diff(&new), pad::MetaDiff::default(), "self diff mismatch");
+}
+
+#[test]
+fn extract_diff() {
+    // Only new and changed records are extracted.
+    let old = fixture("extract_diff_old", &[("a/", "same.bin", b"same"), ("a/", "edit.bin", b"edit"), ("a/", "gone.bin", b"x")]);
+    let new = fixture("extract_diff_new", &[("a/", "same.bin", b"same"), ("a/", "edit.bin", b"edited"), ("b/", "new.bin", b"new")]);
+    let out = new.root.join("out");
+    let stats = new.extract_diff(&old, &ReadLevel::Raw, &out).expect("extract failed");
+    assert_eq!(stats.files, 2, "extracted count mismatch");
+    assert!(!out.join("a/same.bin").exists(), "unchanged record extracted");
+    assert_eq!(std::fs::read(out.join("a/edit.bin")).unwrap(), b"edited", "changed contents mismatch");
+    assert_eq!(std::fs::read(out.join("b/new.bin")).unwrap(), b"new", "added contents mismatch");
+}
+
+#[test]
+fn extract_paths() {
+    // Only the listed paths are extracted and unknown ones are reported back.
+    let meta = fixture("extract_paths", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two"), ("b/", "three.bin", b"three")]);
+    let out = meta.root.join("out");
+    let stats = meta
+        .extract_paths(&["b/three.bin", "a/one.bin", "a/nope.bin"], &ReadLevel::Raw, &out)
+        .expect("extract failed");
+    assert_eq!(stats.files, 2, "extracted count mismatch");
+    assert_eq!(stats.not_found, ["a/nope.bin"], "not found mismatch");
+    assert_eq!(std::fs::read(out.join("a/one.bin")).unwrap(), b"one", "one contents mismatch");
+    assert_eq!(std::fs::read(out.join("b/three.bin")).unwrap(), b"three", "three contents mismatch");
+    assert!(!out.join("a/two.bin").exists(), "unlisted record extracted");
+}
+
+#[test]
+fn rekey() {
+    // Package reads use the new key while the parsed tables are kept.
+    let ice = Ice::new(0, KEY);
+    let mut block = b"encrypted block!".to_vec();
+    ice.encrypt_par(&mut block);
+    let mut meta = fixture("rekey", &[("a/", "one.bin", &block)]);
+    let other = [1, 2, 3, 4, 5, 6, 7, 8];
+    meta.rekey(&other).expect("rekey failed");
+    assert_eq!(meta.key(), &other, "key mismatch");
+    let decrypted = meta.read(&meta.meta_table[0], &ReadLevel::Decrypt).expect("read failed");
+    assert_ne!(decrypted, b"encrypted block!", "read with the old key");
+    assert_eq!(meta.list(), ["a/one.bin"], "tables changed");
+    meta.rekey(KEY).expect("rekey failed");
+    let decrypted = meta.read(&meta.meta_table[0], &ReadLevel::Decrypt).expect("read failed");
+    assert_eq!(decrypted, b"encrypted block!", "rekeyed read mismatch");
+}
+
+#[test]
+fn key_lengths() {
+    // Longer keys pick a higher ICE level, and lengths ICE can't take are refused.
+    let long_key: Vec<u8> = (1..=16).collect();
+    let mut block = b"encrypted block!".to_vec();
+    Ice::new(2, &long_key).encrypt_par(&mut block);
+    let mut meta = fixture("key_lengths", &[("a/", "one.bin", &block)]);
+    meta.rekey(&long_key).expect("rekey failed");
+    let decrypted = meta.read(&meta.meta_table[0], &ReadLevel::Decrypt).expect("read failed");
+    assert_eq!(decrypted, b"encrypted block!", "long key read mismatch");
+    for len in [0, 7, 12] {
+        let err = meta.rekey(&vec![0; len]).expect_err("rekeyed with a bad length");
+        assert!(matches!(err, pad::PadError::BadKeyLength(l) if l == len), "unexpected error: {}", err);
+    }
+    assert_eq!(meta.key(), &long_key[..], "key changed by a failed rekey");
+}
+
+#[test]
+fn extract_one() {
+    // A single file is located by its logical path; unknown paths aren't an error.
+    let meta = fixture("extract_one", &[("a/b/", "one.bin", b"one"), ("a/", "two.bin", b"two")]);
+    let out = meta.root.join("out");
+    assert!(meta.extract_one("a/two.bin", &ReadLevel::Raw, &out).expect("extract failed"));
+    assert_eq!(std::fs::read(out.join("a/two.bin")).unwrap(), b"two", "output bytes mismatch");
+    assert!(!out.join("a/b/one.bin").exists(), "extracted more than one file");
+    assert!(!meta.extract_one("a/missing.bin", &ReadLevel::Raw, &out).expect("extract failed"));
+}
+
+#[test]
+fn extract_progress() {
+    // The progress callback fires once per record with a running count.
+    let meta = fixture(
+        "extract_progress",
+        &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two"), ("../", "bad.bin", b"bad")],
+    );
+    let out = meta.root.join("out");
+    let calls = std::sync::Mutex::new(Vec::new());
+    let stats = meta
+        .extract_many_with_progress(&ReadLevel::Raw, &out, |done| calls.lock().unwrap().push(done))
+        .expect("extract failed");
+    assert_eq!((stats.files, stats.bytes), (2, 6), "extract stats mismatch");
+    let mut calls = calls.into_inner().unwrap();
+    calls.sort_unstable();
+    assert_eq!(calls, vec![1, 2, 3], "progress calls mismatch");
+}
+
+#[test]
+fn extract_cancelled() {
+    // A cancelled run writes nothing further and says so.
+    let mut meta = fixture("extract_cancelled", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two")]);
+    let out = meta.root.join("out");
+    let cancel = std::sync::atomic::AtomicBool::new(true);
+    let stats = meta.extract_many_cancellable(&ReadLevel::Raw, &out, &cancel).expect("extract failed");
+    assert!(stats.cancelled, "cancellation not reported");
+    assert_eq!(stats.files, 0, "extracted after cancel");
+    assert!(!out.join("a/one.bin").exists(), "extracted after cancel");
+
+    cancel.store(false, std::sync::atomic::Ordering::Relaxed);
+    let stats = meta.extract_many_cancellable(&ReadLevel::Raw, &out, &cancel).expect("extract failed");
+    assert!(!stats.cancelled, "uncancelled run reported cancelled");
+    assert_eq!(stats.files, 2, "extracted count mismatch");
+
+    // Raising the flag with nothing left to skip isn't a cancellation.
+    meta.filter_by_size(100..);
+    cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+    let stats = meta.extract_many_cancellable(&ReadLevel::Raw, &out, &cancel).expect("extract failed");
+    assert!(!stats.cancelled, "nothing skipped but reported cancelled");
+}
+
+#[test]
+fn extract_all_to_memory() {
+    // Every record is decoded into the map under its logical path.
+    let mut meta = fixture("extract_all_to_memory", &[("a/b/", "one.bin", b"one"), ("a/", "two.bin", b"two")]);
+    let files = meta.extract_all_to_memory(&ReadLevel::Raw).expect("extract failed");
+    assert_eq!(files.len(), 2, "file count mismatch");
+    assert_eq!(files[&PathBuf::from("a/b/one.bin")], b"one", "file bytes mismatch");
+    assert_eq!(files[&PathBuf::from("a/two.bin")], b"two", "file bytes mismatch");
+
+    meta.meta_table[1].package_id = 2;
+    assert!(meta.extract_all_to_memory(&ReadLevel::Raw).is_err(), "missing package ignored");
+}
+
+#[test]
+fn for_each_recompressed() {
+    // Good records reach the callback once each and failed ones come back by hash.
+    let mut meta = fixture("for_each_recompressed", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two"), ("b/", "three.bin", b"three")]);
+    meta.meta_table[1].file_id = 99;
+    meta.meta_table[2].package_id = 2;
+    let seen = std::sync::Mutex::new(Vec::new());
+    let failures = meta.for_each_recompressed(&ReadLevel::Raw, pad::OutCodec::Identity, |path, bytes| {
+        seen.lock().unwrap().push((path.to_path_buf(), bytes.to_vec()));
+    });
+    assert_eq!(seen.into_inner().unwrap(), [(PathBuf::from("a/one.bin"), b"one".to_vec())], "callback mismatch");
+    let failed: Vec<u32> = failures.iter().map(|(hash, _)| *hash).collect();
+    assert_eq!(failed, [1, 2], "failures mismatch");
+}
+
+#[test]
+fn mmap_backend() {
+    // Mapped reads return the same bytes as file reads and a missing package is named.
+    let mut meta = fixture("mmap_backend", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two bytes")]);
+    let file_reads: Vec<_> = meta.meta_table.iter().map(|mr| meta.read(mr, &ReadLevel::Raw).unwrap()).collect();
+    meta.backend = pad::ReadBackend::Mmap;
+    let mmap_reads: Vec<_> = meta.meta_table.iter().map(|mr| meta.read(mr, &ReadLevel::Raw).unwrap()).collect();
+    assert_eq!(file_reads, mmap_reads, "backend reads mismatch");
+
+    // A record running past the end of the package is an error rather than a panic.
+    meta.meta_table[1].sz_compressed = 100;
+    assert!(meta.read(&meta.meta_table[1], &ReadLevel::Raw).is_err(), "overlong read succeeded");
+
+    meta.meta_table[1].package_id = 2;
+    match meta.read(&meta.meta_table[1], &ReadLevel::Raw) {
+        Err(pad::PadError::MissingPackage(path)) => assert!(path.ends_with("PAD00002.paz")),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("missing package read"),
+    }
+}
+
+#[test]
+fn cached_file_backend() {
+    // Cached descriptors return the same bytes as file reads and a missing package is named.
+    let mut meta = fixture("cached_file_backend", &[("a/", "one.bin", b"one"), ("a/", "two.bin", b"two bytes")]);
+    let file_reads: Vec<_> = meta.meta_table.iter().map(|mr| meta.read(mr, &ReadLevel::Raw).unwrap()).collect();
+    meta.backend = pad::ReadBackend::CachedFile;
+    let cached_reads: Vec<_> = meta.meta_table.iter().map(|mr| meta.read(mr, &ReadLevel::Raw).unwrap()).collect();
+    assert_eq!(file_reads, cached_reads, "backend reads mismatch");
+
+    meta.meta_table[1].package_id = 2;
+    match meta.read(&meta.meta_table[1], &ReadLevel::Raw) {
+        Err(pad::PadError::MissingPackage(path)) => assert!(path.ends_with("PAD00002.paz")),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("missing package read"),
+    }
+}
+
+#[test]
+fn extract_order() {
+    // Grouped and table order reads write the same files.
+    let meta = fixture("extract_order", &[("b/", "two.bin", b"two"), ("a/", "one.bin", b"one"), ("a/", "three.bin", b"three")]);
+    for order in [pad::ExtractOrder::ByPackage, pad::ExtractOrder::Table] {
+        let out_dir = meta.root.join(format!("out_{:?}", order));
+        let options = pad::ExtractOptions { order, ..Default::default() };
+        meta.extract_many_with_options(&ReadLevel::Raw, &out_dir, &options).unwrap();
+        assert_eq!(std::fs::read(out_dir.join("a/one.bin")).unwrap(), b"one", "{:?} content mismatch", order);
+        assert_eq!(std::fs::read(out_dir.join("a/three.bin")).unwrap(), b"three", "{:?} content mismatch", order);
+        assert_eq!(std::fs::read(out_dir.join("b/two.bin")).unwrap(), b"two", "{:?} content mismatch", order);
+    }
+}
+
+#[test]
+fn package_checksum() {
+    // The package holds a lookup3 reference string so its expected hash is known.
+    let mut meta = fixture("package_checksum", &[("a/", "score.txt", b"Four score and seven years ago")]);
+    std::sync::Arc::make_mut(&mut meta.package_table).push(pad::PackageRecord { id: 1, hash: 0x17770551, size: 30 });
+    assert!(meta.verify_package(1).unwrap(), "package hash mismatch");
+    assert!(meta.verify_all_packages().is_empty(), "unexpected mismatches");
+
+    std::sync::Arc::make_mut(&mut meta.package_table)[0].hash = 0;
+    assert!(!meta.verify_package(1).unwrap(), "corrupt package verified");
+    let mismatches = meta.verify_all_packages();
+    assert_eq!(mismatches.len(), 1, "mismatch count mismatch");
+    assert_eq!(mismatches[0].computed, Some(0x17770551), "computed hash mismatch");
+    assert!(meta.verify_package(2).is_err(), "unknown package verified");
+}
+
+#[cfg(feature = "zip")]
+#[test]
+fn extract_to_zip() {
+    // Entries keep their forward slash logical paths; unsafe paths are reported, not written.
+    let meta = fixture("extract_to_zip", &[("a/b/", "one.bin", b"one"), ("a/", "two.bin", b"two"), ("../", "bad.bin", b"bad")]);
+    let zip_path = meta.root.join("out.zip");
+    let stats = meta.extract_to_zip(&ReadLevel::Raw, &zip_path).expect("extract failed");
+    assert_eq!((stats.files, stats.bytes, stats.failures.len()), (2, 6, 1), "extract stats mismatch");
+
+    let mut zip = zip::ZipArchive::new(std::fs::File::open(&zip_path).unwrap()).unwrap();
+    assert_eq!(zip.len(), 2, "entry count mismatch");
+    let mut contents = Vec::new();
+    std::io::Read::read_to_end(&mut zip.by_name("a/b/one.bin").expect("entry missing"), &mut contents).unwrap();
+    assert_eq!(contents, b"one", "entry bytes mismatch");
+}
+
+#[cfg(feature = "tar")]
+#[test]
+fn extract_to_tar() {
+    // The stream holds one regular file per record at its logical path.
+    let meta = fixture("extract_to_tar", &[("a/b/", "one.bin", b"one"), ("a/", "two.bin", b"two")]);
+    let mut stream = Vec::new();
+    let stats = meta.extract_to_tar(&ReadLevel::Raw, &mut stream).expect("extract failed");
+    assert_eq!((stats.files, stats.bytes), (2, 6), "extract stats mismatch");
+
+    let mut entries: Vec<(String, Vec<u8>)> = tar::Archive::new(stream.as_slice())
+        .entries()
+        .unwrap()
+        .map(|entry| {
+            let mut entry = entry.unwrap();
+            assert_eq!(entry.header().mode().unwrap(), 0o644, "mode mismatch");
+            let path = entry.path().unwrap().to_str().unwrap().to_string();
+            let mut contents = Vec::new();
+            std::io::Read::read_to_end(&mut entry, &mut contents).unwrap();
+            (path, contents)
+        })
+        .collect();
+    entries.sort();
+    assert_eq!(
+        entries,
+        [("a/b/one.bin".to_string(), b"one".to_vec()), ("a/two.bin".to_string(), b"two".to_vec())],
+        "entries mismatch"
+    );
+}
@@ -7,13 +7,30 @@ lazy_static::lazy_static! {
 
 const KEY: &[u8; 8] = &[0x51, 0xF3, 0x0F, 0x11, 0x04, 0x24, 0x6A, 0x00];
 
+// Assembles an encrypted meta buffer with empty package and meta blocks around the given
+// plaintext path and file blocks.
+fn synthetic_meta(paths: &[u8], files: &[u8]) -> Vec<u8> {
+    let ice = ice::icefast::Ice::new(0, KEY);
+    let mut buf = Vec::new();
+    buf.extend_from_slice(&1u32.to_le_bytes());
+    buf.extend_from_slice(&0u32.to_le_bytes());
+    buf.extend_from_slice(&0u32.to_le_bytes());
+    for block in [paths, files] {
+        let mut block = block.to_vec();
+        ice.encrypt_par(&mut block);
+        buf.extend_from_slice(&(block.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&block);
+    }
+    buf
+}
+
 #[test]
 fn meta_parse() {
     // This is a rather monolithic test, but the structure of the meta file is
     // pretty simple and each block is interdependent. The only part that might
     // be 'different' is the string handling since it has dynamic length but
     // that isn't worth breaking up into multiple tests.
-    let meta = MetaFile::new_from_path(&ROOT, KEY).expect("meta parsing error");
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
 
     // Version
     assert_eq!(meta.version, 1892, "version mismatch");
@@ -71,6 +88,8 @@ fn meta_parse() {
 
     // File table
     assert_eq!(meta.file_table.len(), 597589, "file table len mismatch");
+    assert_eq!(meta.total_file_count(), 597589, "total file count mismatch");
+    assert_eq!(meta.distinct_file_count(), 597589, "distinct file count mismatch");
     assert_eq!(
         meta.file_table.first().unwrap(),
         &PathBuf::from("ai 스크립트_메뉴얼.xml"),
@@ -90,7 +109,7 @@ fn path_filter() {
     // tables.
 
     // Filters without qualifiers.
-    let mut meta = MetaFile::new_from_path(&ROOT, KEY).expect("meta parsing error");
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
     let old_package_table_len = meta.package_table.len();
     let old_path_table_len = meta.path_table.len();
     let old_file_table_len = meta.file_table.len();
@@ -101,7 +120,7 @@ fn path_filter() {
     assert_eq!(meta.meta_table.len(), 156958, "(w/o qualifiers)meta table len mismatch");
 
     // Filters with qualifiers.
-    let mut meta = MetaFile::new_from_path(&ROOT, KEY).expect("meta parsing error");
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
     meta.filter_by_path("^character/ai_.*k/").expect("(w/ qualifiers) path filter error");
     assert_eq!(meta.package_table.len(), old_package_table_len, "(w/ qualifiers) package table len mismatch");
     assert_eq!(meta.path_table.len(), old_path_table_len, "(w/ qualifiers)path table len mismatch");
@@ -116,7 +135,7 @@ fn file_filter() {
     // tables.
 
     // Filters without qualifiers.
-    let mut meta = MetaFile::new_from_path(&ROOT, KEY).expect("meta parsing error");
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
     let old_package_table_len = meta.package_table.len();
     let old_path_table_len = meta.path_table.len();
     let old_file_table_len = meta.file_table.len();
@@ -127,10 +146,697 @@ fn file_filter() {
     assert_eq!(meta.meta_table.len(), 40, "(w/o qualifiers)meta table len mismatch");
 
     // Filters with qualifiers.
-    let mut meta = MetaFile::new_from_path(&ROOT, KEY).expect("meta parsing error");
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
     meta.filter_by_file("^cloud.*fx").expect("(w/ qualifiers) path filter error");
     assert_eq!(meta.package_table.len(), old_package_table_len, "(w/ qualifiers) package table len mismatch");
     assert_eq!(meta.path_table.len(), old_path_table_len, "(w/ qualifiers)path table len mismatch");
     assert_eq!(meta.file_table.len(), old_file_table_len, "(w/ qualifiers)file table len mismatch");
     assert_eq!(meta.meta_table.len(), 4, "(w/ qualifiers)meta table len mismatch");
 }
+
+#[test]
+fn filter_stats() {
+    // The stats variants should report the meta table length on either side of the filter.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let stats = meta.filter_by_path_stats("character").expect("path filter error");
+    assert_eq!(stats.before, 597589, "path filter before mismatch");
+    assert_eq!(stats.after, 156958, "path filter after mismatch");
+    assert_eq!(stats.removed, 597589 - 156958, "path filter removed mismatch");
+
+    let stats = meta.filter_by_file_stats("^cloud.*fx").expect("file filter error");
+    assert_eq!(stats.before, 156958, "file filter before mismatch");
+    assert_eq!(stats.after, meta.meta_table.len(), "file filter after mismatch");
+    assert_eq!(stats.removed, stats.before - stats.after, "file filter removed mismatch");
+}
+
+#[test]
+fn extension_filter() {
+    // Only the final extension counts, in any case, with or without the dot.
+    for (ext, pattern) in [("paac", r"(?i)\.paac$"), (".bss", r"(?i)\.bss$")] {
+        let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+        let stats = meta.filter_by_extension(ext);
+        let mut expected = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+        expected.filter_by_file(pattern).expect("file filter error");
+        assert!(stats.after > 0, "no {} files", ext);
+        assert_eq!(stats.after, expected.meta_table.len(), "{} count mismatch", ext);
+        assert!(
+            meta.list().iter().all(|p| p.to_lowercase().ends_with(&format!(".{}", ext.trim_start_matches('.')))),
+            "{} filter kept another extension",
+            ext
+        );
+    }
+}
+
+#[test]
+fn size_filter() {
+    // Assets of at least 1 MiB, then composed with a file filter.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let stats = meta.filter_by_size(1 << 20..);
+    assert_eq!(stats.after, 9930, "large file count mismatch");
+    assert!(meta.meta_table.iter().all(|mr| mr.sz_original >= 1 << 20), "small file kept");
+    assert_eq!(meta.path_table.len(), 6321, "path table len mismatch");
+    meta.filter_by_file(r"\.dds$").expect("file filter error");
+    assert!(meta.meta_table.len() < 9930, "file filter removed nothing");
+    assert!(meta.meta_table.iter().all(|mr| mr.sz_original >= 1 << 20), "small file kept");
+}
+
+#[test]
+fn package_filter() {
+    // Only the given packages' records remain and the lookup tables are untouched.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let ids = [meta.meta_table[0].package_id, meta.meta_table.last().unwrap().package_id];
+    let expected = meta.meta_table.iter().filter(|mr| ids.contains(&mr.package_id)).count();
+    let stats = meta.filter_by_package(&ids);
+    assert_eq!(stats.after, expected, "package filter count mismatch");
+    assert!(meta.meta_table.iter().all(|mr| ids.contains(&mr.package_id)), "other package kept");
+    assert_eq!(meta.package_table.len(), 7700, "package table len mismatch");
+    assert_eq!(meta.path_table.len(), 6321, "path table len mismatch");
+    assert_eq!(meta.file_table.len(), 597589, "file table len mismatch");
+    assert_eq!(meta.filter_by_package(&[]).after, 0, "empty id list kept records");
+}
+
+#[test]
+fn retain_predicate() {
+    // A predicate combining size, path and name matches the equivalent chain of filters.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let stats = meta.retain(|mr, path, file| {
+        mr.sz_original >= 1 << 20
+            && path.to_str().unwrap().starts_with("character/")
+            && file.extension().is_some_and(|e| e == "dds")
+    });
+    let mut expected = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    expected.filter_by_path("^character/").expect("path filter error");
+    expected.filter_by_size(1 << 20..);
+    expected.filter_by_extension("dds");
+    assert!(stats.after > 0, "nothing retained");
+    assert_eq!(meta.list(), expected.list(), "retained records mismatch");
+}
+
+#[test]
+fn exact_files_filter() {
+    // Exact names match the file name only and need no escaping of dots or Korean characters.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let names: std::collections::HashSet<String> = [
+        "ai 스크립트_메뉴얼.xml".to_string(),
+        "sorceressaction_noweapon_simple.paac".to_string(),
+        "ai 스크립트_메뉴얼_xml".to_string(),
+    ]
+    .into_iter()
+    .collect();
+    meta.filter_by_exact_files(&names);
+    assert_eq!(meta.meta_table.len(), 4, "meta table len mismatch");
+    assert_eq!(meta.meta_table.first().unwrap().file_id, 0, "first file id mismatch");
+    assert_eq!(meta.meta_table.last().unwrap().file_id, 597588, "last file id mismatch");
+}
+
+#[test]
+fn find_by_name() {
+    // Finds the same records as the exact name filter, across directories.
+    let name = "sorceressaction_noweapon_simple.paac";
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let found: Vec<u32> = meta.find_by_name(name).iter().map(|mr| mr.hash).collect();
+    let mut filtered = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    filtered.filter_by_exact_files(&[name.to_string()].into_iter().collect());
+    let expected: Vec<u32> = filtered.meta_table.iter().map(|mr| mr.hash).collect();
+    assert!(!found.is_empty(), "name not found");
+    assert_eq!(found, expected, "found records mismatch");
+    assert!(meta.find_by_name("no such file.bin").is_empty(), "missing name found");
+}
+
+#[test]
+fn package_stats() {
+    // Per package totals add back up to the whole selection.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_path("character/").expect("path filter error");
+    let stats = meta.package_stats();
+    assert!(stats.windows(2).all(|w| w[0].package_id < w[1].package_id), "package order mismatch");
+    let file_count: usize = stats.iter().map(|s| s.file_count).sum();
+    let total_original: u64 = stats.iter().map(|s| s.total_original).sum();
+    let total_compressed: u64 = stats.iter().map(|s| s.total_compressed).sum();
+    assert_eq!(file_count, meta.meta_table.len(), "file count mismatch");
+    assert_eq!(total_original, meta.total_original_size(), "original size mismatch");
+    assert_eq!(total_compressed, meta.total_compressed_size(), "compressed size mismatch");
+    assert_eq!(
+        meta.total_original_size(),
+        meta.meta_table.iter().map(|mr| mr.sz_original as u64).sum::<u64>(),
+        "original size mismatch"
+    );
+    let first = meta.meta_table.first().unwrap();
+    let first_stats = stats.iter().find(|s| s.package_id == first.package_id).expect("package missing");
+    assert!(first_stats.total_compressed >= first.sz_compressed as u64, "compressed size mismatch");
+}
+
+#[test]
+fn sort_by() {
+    // Each key orders the whole selection without adding or dropping records.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_file("\\.xml$").expect("file filter error");
+    let parsed = meta.list();
+    meta.sort_by(pad::MetaSortKey::Package);
+    let by_package = |mr: &pad::MetaRecord| (mr.package_id, mr.package_offset);
+    assert!(meta.meta_table.windows(2).all(|w| by_package(&w[0]) <= by_package(&w[1])), "package order mismatch");
+    meta.sort_by(pad::MetaSortKey::SizeDescending);
+    assert!(meta.meta_table.windows(2).all(|w| w[0].sz_original >= w[1].sz_original), "size order mismatch");
+    let largest = meta.meta_table[0].clone();
+    assert_eq!(meta.find_by_hash(largest.hash).map(|mr| mr.sz_original), Some(largest.sz_original), "hash lookup mismatch");
+    meta.sort_by(pad::MetaSortKey::FileId);
+    assert!(meta.meta_table.windows(2).all(|w| w[0].file_id <= w[1].file_id), "file id order mismatch");
+    let (mut sorted, mut parsed) = (meta.list(), parsed);
+    sorted.sort_unstable();
+    parsed.sort_unstable();
+    assert_eq!(sorted, parsed, "sorted records mismatch");
+}
+
+#[test]
+fn deterministic_order() {
+    // Records sharing a file id are ordered by package and offset, so every load agrees.
+    let first = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let second = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    assert_eq!(format!("{:?}", first.meta_table), format!("{:?}", second.meta_table), "meta table order mismatch");
+    let order = |mr: &pad::MetaRecord| (mr.file_id, mr.package_id, mr.package_offset);
+    assert!(first.meta_table.windows(2).all(|w| order(&w[0]) <= order(&w[1])), "tiebreak order mismatch");
+}
+
+#[test]
+fn path_filter_after_sort() {
+    // Path filters select the same records whatever order the meta table is in.
+    let mut expected = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    expected.filter_by_path("^character/").expect("path filter error");
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.sort_by(pad::MetaSortKey::Package);
+    meta.filter_by_path("^character/").expect("path filter error");
+    assert_eq!(meta.meta_table.len(), expected.meta_table.len(), "filtered len mismatch");
+    let hashes = |meta: &MetaFile| {
+        let mut hashes: Vec<_> = meta.meta_table.iter().map(|mr| (mr.hash, mr.package_id)).collect();
+        hashes.sort_unstable();
+        hashes
+    };
+    assert_eq!(hashes(&meta), hashes(&expected), "filtered records mismatch");
+    assert!(meta.list().iter().all(|p| p.starts_with("character/")), "filtered path mismatch");
+    meta.filter_by_file("\\.dds$").expect("file filter error");
+    meta.filter_by_path("^character/texture/").expect("path filter error");
+    assert!(
+        meta.list().iter().all(|p| p.starts_with("character/texture/") && p.ends_with(".dds")),
+        "chained filter mismatch"
+    );
+}
+
+#[test]
+fn builder() {
+    // The builder's defaults parse the same tables as `new`, and a key is required.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let built = MetaFile::builder()
+        .key(KEY)
+        .meta_name("pad00000.meta")
+        .encoding(encoding_rs::EUC_KR)
+        .backend(pad::ReadBackend::Mmap)
+        .build(&ROOT)
+        .expect("builder parsing error");
+    assert_eq!(built.list(), meta.list(), "paths mismatch");
+    assert_eq!(built.backend, pad::ReadBackend::Mmap, "backend mismatch");
+    let err = MetaFile::builder().build(&ROOT).expect_err("built without a key");
+    assert!(matches!(err, pad::PadError::MissingKey), "unexpected error: {}", err);
+}
+
+#[test]
+fn fs_source() {
+    // The filesystem source reads the same meta as `new`.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let from_source = MetaFile::from_source(pad::FsSource::new(&ROOT), KEY).expect("source parsing error");
+    assert_eq!(from_source.list(), meta.list(), "paths mismatch");
+    let err = MetaFile::from_source(pad::FsSource::with_meta(&ROOT, "missing.meta".as_ref()), KEY)
+        .expect_err("parsed a missing meta");
+    assert!(matches!(err, pad::PadError::Io(_)), "unexpected error: {}", err);
+}
+
+#[test]
+fn orphan_records() {
+    // Every record in the test data sits within its path bucket.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    assert!(meta.orphan_records().is_empty(), "unexpected orphan records");
+
+    // Pointing a record at another bucket or past the path table orphans it.
+    meta.meta_table[0].path_id = 6320;
+    meta.meta_table[1].path_id = 6321;
+    assert_eq!(meta.orphan_records(), vec![0, 1], "orphan records mismatch");
+}
+
+#[test]
+fn streaming_parse() {
+    // Streaming hands every meta record to the callback and leaves the meta table empty while the
+    // lookup tables are still parsed.
+    let mut count = 0;
+    let mut retained = Vec::new();
+    let meta = MetaFile::parse_streaming(&ROOT, KEY, |mr| {
+        count += 1;
+        if mr.file_id == 597588 {
+            retained.push(mr);
+        }
+    })
+    .expect("meta parsing error");
+    assert_eq!(count, 597589, "streamed record count mismatch");
+    assert!(meta.meta_table.is_empty(), "meta table should be empty");
+    assert_eq!(meta.path_table.len(), 6321, "path table len mismatch");
+    assert_eq!(meta.file_table.len(), 597589, "file table len mismatch");
+    assert_eq!(retained.len(), 1, "retained len mismatch");
+    assert_eq!(retained[0].hash, 1207248531, "retained hash mismatch");
+}
+
+#[test]
+fn full_report() {
+    // The test data only ships the meta so every package is reported missing, everything else
+    // should be consistent.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let report = meta.full_report();
+    assert_eq!(report.bad_path_ranges.count, 0, "bad path ranges mismatch");
+    assert_eq!(report.bad_file_ids.count, 0, "bad file ids mismatch");
+    assert_eq!(report.orphan_records.count, 0, "orphan records mismatch");
+    assert_eq!(report.path_collisions.count, 0, "path collisions mismatch");
+    assert_eq!(report.package_mismatches.count, 7700, "package mismatches mismatch");
+    assert_eq!(report.package_mismatches.samples.len(), 10, "package samples len mismatch");
+    assert!(report.package_mismatches.samples[0].actual_size.is_none(), "package should be missing");
+    assert!(!report.is_healthy(), "report should be unhealthy");
+}
+
+#[test]
+fn missing_meta() {
+    // A root without pad00000.meta is reported as such rather than as a bare io error.
+    let root: PathBuf = [r".", "test-data", "missing"].iter().collect();
+    match MetaFile::new(&root, KEY) {
+        Err(pad::PadError::MissingMeta(path)) => assert_eq!(path, root.join("pad00000.meta")),
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("missing meta parsed"),
+    }
+}
+
+#[test]
+fn explicit_meta_path() {
+    // Relative and absolute meta paths load the same tables as the default name.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let relative = MetaFile::new_with_meta(&ROOT, std::path::Path::new("pad00000.meta"), KEY)
+        .expect("meta parsing error");
+    let absolute_path = std::fs::canonicalize(ROOT.join("pad00000.meta")).unwrap();
+    let absolute = MetaFile::new_with_meta(&ROOT, &absolute_path, KEY).expect("meta parsing error");
+    for other in [relative, absolute] {
+        assert_eq!(other.version, meta.version, "version mismatch");
+        assert_eq!(other.meta_table.len(), meta.meta_table.len(), "meta table len mismatch");
+        assert_eq!(other.file_table, meta.file_table, "file table mismatch");
+        assert_eq!(other.root, meta.root, "root mismatch");
+    }
+}
+
+#[test]
+fn meta_from_bytes() {
+    // In memory contents parse like the file and keep the given root for packages.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let bytes = std::fs::read(ROOT.join("pad00000.meta")).unwrap();
+    let root = PathBuf::from("elsewhere");
+    let from_bytes = MetaFile::from_bytes(bytes, root.clone(), KEY).expect("meta parsing error");
+    assert_eq!(from_bytes.root, root, "root mismatch");
+    assert_eq!(from_bytes.version, meta.version, "version mismatch");
+    assert_eq!(from_bytes.list(), meta.list(), "paths mismatch");
+    assert!(from_bytes.package_path(&from_bytes.meta_table[0]).starts_with(&root), "package path mismatch");
+}
+
+#[test]
+fn merged_metas() {
+    // Merging a meta with itself doubles every table and the second copy resolves to the same
+    // paths as the first.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let meta_file = std::path::Path::new("pad00000.meta");
+    let mut merged = MetaFile::new_many(&ROOT, &[meta_file, meta_file], KEY).expect("meta parsing error");
+    assert_eq!(merged.package_table.len(), meta.package_table.len(), "package table len mismatch");
+    assert_eq!(merged.meta_table.len(), 2 * meta.meta_table.len(), "meta table len mismatch");
+    assert_eq!(merged.path_table.len(), 2 * meta.path_table.len(), "path table len mismatch");
+    assert_eq!(merged.file_table.len(), 2 * meta.file_table.len(), "file table len mismatch");
+
+    let paths = meta.list();
+    let merged_paths = merged.list();
+    assert_eq!(merged_paths[..paths.len()], paths[..], "first copy paths mismatch");
+    assert_eq!(merged_paths[paths.len()..], paths[..], "second copy paths mismatch");
+    assert!(merged.validate_path_ranges().is_empty(), "merged path ranges invalid");
+
+    let mut filtered = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    filtered.filter_by_path("character/").expect("path filter error");
+    merged.filter_by_path("character/").expect("path filter error");
+    assert_eq!(merged.meta_table.len(), 2 * filtered.meta_table.len(), "path filter mismatch");
+}
+
+#[test]
+fn unpadded_path_block() {
+    // A path block ending on its last character, without a terminator or padding, keeps that
+    // character and isn't read past.
+    let mut paths = Vec::new();
+    paths.extend_from_slice(&0u32.to_le_bytes());
+    paths.extend_from_slice(&1u32.to_le_bytes());
+    paths.extend_from_slice(b"ab/\0");
+    paths.extend_from_slice(&1u32.to_le_bytes());
+    paths.extend_from_slice(&1u32.to_le_bytes());
+    paths.extend_from_slice(b"abc/");
+    assert_eq!(paths.len() % 8, 0, "path block must be whole cipher blocks");
+    let mut buf = synthetic_meta(&paths, b"one.bin\0two.bin\0");
+    let meta = MetaFile::from_buffer(&mut buf, KEY).expect("meta parsing error");
+    assert_eq!(meta.path_table.len(), 2, "path table len mismatch");
+    assert_eq!(meta.path_table[0].path, PathBuf::from("ab/"), "first path mismatch");
+    assert_eq!(meta.path_table[1].path, PathBuf::from("abc/"), "last path mismatch");
+    assert_eq!(meta.path_table[1].file_range, 1..2, "last file range mismatch");
+    assert_eq!(*meta.file_table, [PathBuf::from("one.bin"), PathBuf::from("two.bin")], "file table mismatch");
+}
+
+#[test]
+fn utf8_names() {
+    // Names decode with the encoding given instead of EUC_KR.
+    let mut paths = Vec::new();
+    paths.extend_from_slice(&0u32.to_le_bytes());
+    paths.extend_from_slice(&1u32.to_le_bytes());
+    paths.extend_from_slice("한글/\0".as_bytes());
+    let files = "파일.txt\0\0\0\0\0\0".as_bytes();
+    let bytes = synthetic_meta(&paths, files);
+    let meta = MetaFile::from_bytes_with_encoding(bytes, PathBuf::new(), KEY, encoding_rs::UTF_8)
+        .expect("meta parsing error");
+    assert_eq!(meta.path_table[0].path, PathBuf::from("한글/"), "path mismatch");
+    assert_eq!(*meta.file_table, [PathBuf::from("파일.txt")], "file table mismatch");
+}
+
+#[test]
+fn decode_warnings() {
+    // Names that aren't valid in the chosen encoding are reported rather than silently mangled.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    assert!(meta.decode_warnings().is_empty(), "unexpected EUC_KR warnings");
+
+    let mut paths = Vec::new();
+    paths.extend_from_slice(&0u32.to_le_bytes());
+    paths.extend_from_slice(&2u32.to_le_bytes());
+    paths.extend_from_slice(b"ascii/\0");
+    let files = [&b"plain.txt\0"[..], &[0xFF, 0xFE, b'.', b'x', 0, 0, 0, 0, 0]].concat();
+    let bytes = synthetic_meta(&paths, &files);
+    let meta = MetaFile::from_bytes(bytes, PathBuf::new(), KEY).expect("meta parsing error");
+    let warnings = meta.decode_warnings();
+    assert_eq!(warnings.len(), 1, "warning count mismatch");
+    assert_eq!((warnings[0].table, warnings[0].index), ("file", 1), "warning entry mismatch");
+
+    // A name that really holds U+FFFD decoded cleanly and isn't reported.
+    let files = ["\u{FFFD}.txt\0".as_bytes(), &[0xFF, b'.', b'y', 0, 0, 0, 0, 0]].concat();
+    let bytes = synthetic_meta(&paths, &files);
+    let meta = MetaFile::from_bytes_with_encoding(bytes, PathBuf::new(), KEY, encoding_rs::UTF_8)
+        .expect("meta parsing error");
+    let warnings = meta.decode_warnings();
+    assert_eq!(warnings.len(), 1, "(UTF-8) warning count mismatch");
+    assert_eq!((warnings[0].table, warnings[0].index), ("file", 1), "(UTF-8) warning entry mismatch");
+}
+
+#[test]
+fn empty_blocks() {
+    // Zero length blocks parse to empty tables, as does a block of nothing but padding.
+    let mut buf = synthetic_meta(&[], &[]);
+    let meta = MetaFile::from_buffer(&mut buf, KEY).expect("meta parsing error");
+    assert!(meta.package_table.is_empty(), "package table not empty");
+    assert!(meta.meta_table.is_empty(), "meta table not empty");
+    assert!(meta.path_table.is_empty(), "path table not empty");
+    assert!(meta.file_table.is_empty(), "file table not empty");
+
+    let mut buf = synthetic_meta(&[0; 8], &[0; 8]);
+    let meta = MetaFile::from_buffer(&mut buf, KEY).expect("meta parsing error");
+    assert!(meta.path_table.is_empty(), "padded path table not empty");
+    assert!(meta.file_table.is_empty(), "padded file table not empty");
+
+    // A count running past the end of the buffer is an error rather than a panic.
+    let mut buf = synthetic_meta(&[], &[]);
+    buf[4..8].copy_from_slice(&100u32.to_le_bytes());
+    assert!(MetaFile::from_buffer(&mut buf, KEY).is_err(), "truncated meta parsed");
+}
+
+#[test]
+fn clear_filters() {
+    // Filters stack until cleared, after which a different filter sees the full table again.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let all = meta.list();
+    meta.filter_by_path("character/").expect("path filter error");
+    meta.filter_by_file("^phm_").expect("file filter error");
+    assert!(meta.meta_table.len() < all.len(), "filters removed nothing");
+    meta.clear_filters();
+    assert_eq!(meta.list(), all, "cleared table mismatch");
+
+    let mut fresh = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    fresh.filter_by_path("ui/").expect("path filter error");
+    meta.filter_by_path("ui/").expect("path filter error");
+    assert_eq!(meta.list(), fresh.list(), "refiltered table mismatch");
+    meta.clear_filters();
+    meta.clear_filters();
+    assert_eq!(meta.meta_table.len(), all.len(), "repeated clear mismatch");
+}
+
+#[test]
+fn find_by_hash() {
+    // Lookups resolve against the current selection and follow filters.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let record = meta.meta_table[1000].clone();
+    let found = meta.find_by_hash(record.hash).expect("hash not found");
+    assert_eq!(found.hash, record.hash, "hash mismatch");
+    assert_eq!(found.package_offset, record.package_offset, "record mismatch");
+
+    let path = meta.path_table[record.path_id as usize].path.to_str().unwrap().to_string();
+    meta.filter_by_path(&format!("^{}$", regex::escape(&path))).expect("path filter error");
+    assert!(meta.find_by_hash(record.hash).is_some(), "filtered hash not found");
+    meta.filter_by_path("^$").expect("path filter error");
+    assert!(meta.find_by_hash(record.hash).is_none(), "removed hash found");
+    meta.clear_filters();
+    assert!(meta.find_by_hash(record.hash).is_some(), "cleared hash not found");
+}
+
+#[test]
+fn meta_view() {
+    // Filtering a view selects the same records as filtering the table, which stays untouched.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let mut view = meta.view();
+    assert_eq!(view.len(), meta.meta_table.len(), "view len mismatch");
+    view.filter_by_path("character/").expect("path filter error");
+    view.filter_by_file("\\.paac$").expect("file filter error");
+
+    let mut filtered = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    filtered.filter_by_path("character/").expect("path filter error");
+    filtered.filter_by_file("\\.paac$").expect("file filter error");
+    let view_hashes: Vec<u32> = view.records().map(|mr| mr.hash).collect();
+    let filtered_hashes: Vec<u32> = filtered.meta_table.iter().map(|mr| mr.hash).collect();
+    assert!(!view.is_empty(), "view empty");
+    assert_eq!(view_hashes, filtered_hashes, "view records mismatch");
+    assert_eq!(meta.meta_table.len(), meta.view().len(), "meta table changed");
+    assert!(view.read(usize::MAX, &pad::ReadLevel::Raw).is_err(), "out of range index read");
+
+    let options = pad::FilterOptions { case_insensitive: true };
+    let mut view = meta.view();
+    view.filter_by_path_with("CHARACTER/", &options).expect("path filter error");
+    view.filter_by_file_with("\\.PAAC$", &options).expect("file filter error");
+    assert_eq!(view.records().map(|mr| mr.hash).collect::<Vec<_>>(), filtered_hashes, "(case-insensitive) view records mismatch");
+}
+
+#[test]
+fn invalid_filter_pattern() {
+    // A bad pattern is an error rather than a panic and leaves the meta table untouched.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let old_meta_table_len = meta.meta_table.len();
+    let err = meta.filter_by_path("[").expect_err("invalid path pattern accepted");
+    assert!(matches!(err, pad::PadError::Regex(_)), "unexpected error: {}", err);
+    let err = meta.filter_by_file("[").expect_err("invalid file pattern accepted");
+    assert!(matches!(err, pad::PadError::Regex(_)), "unexpected error: {}", err);
+    assert_eq!(meta.meta_table.len(), old_meta_table_len, "meta table len mismatch");
+}
+
+#[test]
+fn entries() {
+    // Entries pair each meta record with its reconstructed relative path.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_path("^character/ai_.*k/").expect("path filter error");
+    assert_eq!(meta.entries().count(), 37, "entries count mismatch");
+    for (record, path) in meta.entries() {
+        assert!(path.starts_with("character"), "entry path mismatch");
+        assert_eq!(path.file_name().unwrap(), meta.file_table[record.file_id as usize], "entry file name mismatch");
+        assert_eq!(meta.logical_path(record).expect("logical path error"), path, "logical path mismatch");
+    }
+}
+
+#[test]
+fn glob_filters() {
+    // Globs match the same decoded strings as the regex filters.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_path_glob("character/ai_*k/*").expect("path glob error");
+    assert_eq!(meta.meta_table.len(), 37, "path glob meta table len mismatch");
+
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_file_glob("cloud*fx*").expect("file glob error");
+    assert_eq!(meta.meta_table.len(), 4, "file glob meta table len mismatch");
+}
+
+#[test]
+fn case_insensitive_filter() {
+    // Case folding is opt in; the default stays case sensitive.
+    let options = pad::FilterOptions { case_insensitive: true };
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_file_with("^CLOUD.*FX", &options).expect("file filter error");
+    assert_eq!(meta.meta_table.len(), 4, "(case insensitive) meta table len mismatch");
+
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_file("^CLOUD.*FX").expect("file filter error");
+    assert_eq!(meta.meta_table.len(), 0, "(case sensitive) meta table len mismatch");
+}
+
+#[test]
+fn combined_filter() {
+    // A single pass over both patterns matches chaining the path and file filters.
+    let mut chained = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    chained.filter_by_path("character").expect("path filter error");
+    chained.filter_by_file("^cloud").expect("file filter error");
+
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter(Some("character"), Some("^cloud")).expect("filter error");
+    assert_eq!(meta.meta_table.len(), chained.meta_table.len(), "meta table len mismatch");
+
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter(Some("^character/ai_.*k/"), None).expect("filter error");
+    assert_eq!(meta.meta_table.len(), 37, "(path only) meta table len mismatch");
+
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter(None, None).expect("filter error");
+    assert_eq!(meta.meta_table.len(), 597589, "(no patterns) meta table len mismatch");
+
+    let options = pad::FilterOptions { case_insensitive: true };
+    let mut chained = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    chained.filter_by_path_with("CHARACTER", &options).expect("path filter error");
+    chained.filter_by_file_with("^CLOUD", &options).expect("file filter error");
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let stats = meta.filter_with(Some("CHARACTER"), Some("^CLOUD"), &options).expect("filter error");
+    assert_eq!(stats.after, chained.meta_table.len(), "(case-insensitive) meta table len mismatch");
+}
+
+#[test]
+fn list() {
+    // Listing yields one forward slash separated logical path per meta record.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let list = meta.list();
+    assert_eq!(list.len(), 597589, "list len mismatch");
+    assert_eq!(list.first().unwrap(), "character/ai 스크립트_메뉴얼.xml", "first path mismatch");
+    assert_eq!(
+        list.last().unwrap(),
+        "character/rebootbinaryactionchart/rebootpc/2_phw/sorceressaction_noweapon_simple.paac",
+        "last path mismatch"
+    );
+}
+
+#[test]
+fn manifest_csv() {
+    // One row per meta record after the header. No test data name holds a newline so each row is
+    // a single line.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_path("character").expect("path filter error");
+    let mut buf = Vec::new();
+    meta.write_manifest_csv(&mut buf).expect("manifest error");
+    let csv = String::from_utf8(buf).expect("manifest is not utf8");
+    let mut rows = csv.lines();
+    assert_eq!(
+        rows.next().unwrap(),
+        "path,file,package_id,package_offset,sz_compressed,sz_original,hash",
+        "header mismatch"
+    );
+    assert_eq!(rows.next().unwrap(), "character/ai 스크립트_메뉴얼.xml,ai 스크립트_메뉴얼.xml,1,53372,22992,88220,3751579307", "first row mismatch");
+    assert_eq!(rows.count() + 1, meta.meta_table.len(), "row count mismatch");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn manifest_json() {
+    // The json manifest carries the version, every package and one entry per meta record.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_path("^character/ai_.*k/").expect("path filter error");
+    let json = meta.to_manifest_json().expect("manifest error");
+    let manifest: serde_json::Value = serde_json::from_str(&json).expect("manifest is not json");
+    assert_eq!(manifest["version"], 1892, "version mismatch");
+    assert_eq!(manifest["packages"].as_array().unwrap().len(), 7700, "packages len mismatch");
+    let entries = manifest["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 37, "entries len mismatch");
+    assert!(entries[0]["path"].as_str().unwrap().starts_with("character/ai_"), "entry path mismatch");
+    assert_eq!(entries[0]["sz_original"], meta.meta_table[0].sz_original, "entry size mismatch");
+}
+
+#[test]
+fn block_offsets() {
+    // Each block starts right after its u32 count and the file block ends the meta file.
+    let bytes = std::fs::read(ROOT.join("pad00000.meta")).expect("meta read error");
+    let offsets = MetaFile::block_offsets(&bytes).expect("block offsets error");
+    assert_eq!(offsets.packages.start, 8, "packages start mismatch");
+    assert_eq!(offsets.packages.len(), 7700 * 12, "packages len mismatch");
+    assert_eq!(offsets.metas.start, offsets.packages.end + 4, "metas start mismatch");
+    assert_eq!(offsets.paths.start, offsets.metas.end + 4, "paths start mismatch");
+    assert_eq!(offsets.files.start, offsets.paths.end + 4, "files start mismatch");
+    assert_eq!(offsets.files.end, bytes.len(), "files end mismatch");
+
+    let bytes = synthetic_meta(&[0; 8], &[0; 16]);
+    let offsets = MetaFile::block_offsets(&bytes).expect("block offsets error");
+    assert_eq!(offsets.paths, 16..24, "paths range mismatch");
+    assert_eq!(offsets.files, 28..44, "files range mismatch");
+    assert!(MetaFile::block_offsets(&bytes[..40]).is_err(), "truncated meta should fail");
+}
+
+#[test]
+fn describe() {
+    // Records render with their logical path and readable sizes.
+    let mut meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    meta.filter_by_path("character").expect("path filter error");
+    let record = meta.meta_table[0].clone();
+    assert_eq!(
+        meta.describe(&record),
+        "character/ai 스크립트_메뉴얼.xml (package 1, 22.5 KiB stored, 86.2 KiB original, hash df9c8eab)",
+        "description mismatch"
+    );
+    let mut small = record.clone();
+    small.sz_compressed = 100;
+    small.sz_original = 3 * 1024 * 1024;
+    assert!(meta.describe(&small).ends_with("100 B stored, 3.0 MiB original, hash df9c8eab)"), "size units mismatch");
+    let mut bad = record;
+    bad.path_id = u32::MAX;
+    assert!(meta.describe(&bad).starts_with(&format!("<unresolved path {} file", u32::MAX)), "unresolved mismatch");
+}
+
+#[test]
+fn clone_filtered() {
+    // Filtered copies share the name tables and leave the original selection alone.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let characters = meta.clone_filtered(Some("^character/"), None).expect("clone error");
+    let xml = meta.clone_filtered(None, Some("\\.xml$")).expect("clone error");
+    let mut expected = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    expected.filter(Some("^character/"), None).expect("filter error");
+    assert_eq!(characters.list(), expected.list(), "filtered records mismatch");
+    assert!(xml.list().iter().all(|path| path.ends_with(".xml")), "file filter mismatch");
+    assert_eq!(meta.meta_table.len(), meta.file_table.len(), "original selection changed");
+    assert!(std::sync::Arc::ptr_eq(&meta.path_table, &characters.path_table), "path table copied");
+    assert!(std::sync::Arc::ptr_eq(&meta.file_table, &xml.file_table), "file table copied");
+}
+
+#[test]
+fn count_matching() {
+    // Counts agree with the filters they preview and leave the selection as it was.
+    let meta = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    let path_count = meta.count_matching_path("^character/").expect("count error");
+    let file_count = meta.count_matching_file("\\.xml$").expect("count error");
+    assert_eq!(meta.meta_table.len(), meta.file_table.len(), "selection changed");
+    let mut filtered = MetaFile::new(&ROOT, KEY).expect("meta parsing error");
+    filtered.filter_by_path("^character/").expect("path filter error");
+    assert_eq!(path_count, filtered.meta_table.len(), "path count mismatch");
+    assert_eq!(filtered.count_matching_file("\\.xml$").unwrap(), filtered.list().iter().filter(|p| p.ends_with(".xml")).count(), "narrowed count mismatch");
+    filtered.clear_filters();
+    filtered.filter_by_file("\\.xml$").expect("file filter error");
+    assert_eq!(file_count, filtered.meta_table.len(), "file count mismatch");
+    assert!(meta.count_matching_path("(").is_err(), "bad pattern counted");
+
+    let options = pad::FilterOptions { case_insensitive: true };
+    let path_count = meta.count_matching_path_with("^CHARACTER/", &options).expect("count error");
+    let file_count = meta.count_matching_file_with("\\.XML$", &options).expect("count error");
+    filtered.clear_filters();
+    let stats = filtered.filter_with(Some("^CHARACTER/"), None, &options).expect("filter error");
+    assert_eq!(path_count, stats.after, "(case-insensitive) path count mismatch");
+    filtered.clear_filters();
+    let stats = filtered.filter_out_path_with("^CHARACTER/", &options).expect("filter error");
+    assert_eq!(path_count, stats.removed, "(excluded) path count mismatch");
+    filtered.clear_filters();
+    let stats = filtered.filter_out_file_with("\\.XML$", &options).expect("filter error");
+    assert_eq!(file_count, stats.removed, "(excluded) file count mismatch");
+}